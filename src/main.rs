@@ -2,25 +2,36 @@
 use dioxus::prelude::*;
 use meval::eval_str;
 use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, KeyInit},
     Aes256Gcm, Nonce,
 };
-use sha2::{Sha256, Digest};
-use rand::RngCore;
 use std::fs;
-use std::path::PathBuf;
-use serde::{Serialize, Deserialize};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+use dioxus_desktop::tao::event::{Event, WindowEvent};
+use dioxus_desktop::use_wry_event_handler;
+use zeroize::{Zeroize, Zeroizing};
 
-#[derive(Clone, Serialize, Deserialize)]
-struct EncryptedFile {
-    name: String,
-    original_extension: String,
-    encrypted_path: String,
-    size: u64,
-    created_at: String,
-}
+mod vault;
+use vault::{
+    add_key_slot, change_password, check_index_freshness, clear_duress_code, code_vault_dir,
+    codes_configured, compress_reader, content_mac, destroy_vault, file_identity, find_orphans,
+    generate_file_id, generate_key, generate_recovery_key, is_compressible, legacy_key, load_index,
+    match_code, open_vault, quarantine, record_failed_unlock, recover_vault, refresh_attempts,
+    remove_key_slot, reset_failed_unlocks, save_index, secure_delete, set_duress_code,
+    set_unlock_code, suppress_core_dumps, unlock_delay, unwrap_key, wrap_key, AttemptsKey,
+    CipherKind, CodeMatch, ContainerHeader, CoreDumpGuard, Credential, DecompressWriter, DuressMode,
+    EncryptedFile, FileMetadata, HashingReader, HashingSink, KeyScheme, Layout, PaddingPolicy,
+    ScrubReport, SecretKey, SlotKind, TotpConfig, UnpadWriter, VaultHeader,
+    DEFAULT_AUTO_LOCK_MINUTES, DEFAULT_SHRED_PASSES, FLAG_COMPRESSED, FLAG_PADDED, SNIFF_LEN,
+};
 
 fn main() {
+    // سجل محاولات الفتح يُعاد حفظه مع كل تشغيل فلا يدل وقت تعديله على محاولة
+    let _ = refresh_attempts();
     dioxus::launch(app);
 }
 
@@ -32,10 +43,72 @@ fn app() -> Element {
     let mut encrypted_files = use_signal(Vec::<EncryptedFile>::new);
     let mut selected_file_index = use_signal(|| None::<usize>);
     let mut upload_message = use_signal(String::new);
+    // تقدم ترحيل الملفات القديمة - منفصل حتى لا يحجب تحذيرات الفتح في رسالة الرفع
+    let mut migration_message = use_signal(String::new);
 
-    use_effect(move || {
-        load_encrypted_files(&mut encrypted_files);
-    });
+    // مفتاح الخزنة يبقى في الذاكرة فقط طالما الخزنة مفتوحة - مقفولاً ويُمسح عند الإغلاق
+    let mut vault_key = use_signal(|| None::<SecretKey>); // المفتاح الرئيسي
+    let mut attempts_key = use_signal(|| None::<AttemptsKey>); // سجل محاولات الخزنة المعروضة
+    let mut core_dumps = use_signal(|| None::<CoreDumpGuard>); // تفريغ الذاكرة معطل أثناء الفتح
+    let mut password_input = use_signal(String::new);
+    let mut password_confirm = use_signal(String::new);
+    let mut keyfile_path = use_signal(String::new); // بديل كلمة المرور عند الفتح بملف مفتاح
+    let mut recovery_phrase = use_signal(String::new);
+    let mut recovering = use_signal(|| false); // شاشة الاسترداد بدل شاشة كلمة المرور
+    let mut auth_error = use_signal(String::new);
+    let vault_cipher = use_signal(CipherKind::default); // تُختار عند إنشاء الخزنة فقط
+    let mut padding_policy = use_signal(PaddingPolicy::default);
+    let mut compression = use_signal(|| false);
+    let mut shred_passes = use_signal(|| DEFAULT_SHRED_PASSES);
+    let mut wipe_after = use_signal(|| None::<u32>);
+    let mut auto_lock_minutes = use_signal(|| DEFAULT_AUTO_LOCK_MINUTES);
+    let mut lock_on_blur = use_signal(|| false);
+    let mut last_activity = use_signal(SystemTime::now); // يُكتب مع كل حركة ولا يُقرأ في الرسم
+    let mut lock_generation = use_signal(|| 0u64); // يزيد مع كل إغلاق - المهام الجارية تتوقف عنده
+
+    let mut show_change_password = use_signal(|| false);
+    let mut old_password = use_signal(String::new);
+    let mut new_password = use_signal(String::new);
+    let mut new_password_confirm = use_signal(String::new);
+    let mut change_message = use_signal(String::new);
+
+    // فتحات المفاتيح: إضافة كلمة مرور أو مفتاح استرداد أو ملف مفتاح آخر للخزنة نفسها
+    let mut show_key_slots = use_signal(|| false);
+    let mut new_slot_kind = use_signal(SlotKind::default);
+    let mut new_slot_label = use_signal(String::new);
+    let mut new_slot_secret = use_signal(String::new); // كلمة المرور أو مسار ملف المفتاح
+    let mut new_slot_confirm = use_signal(String::new);
+    let mut new_slot_keyfile = use_signal(String::new); // ملف مفتاح يُشترط مع كلمة المرور (اختياري)
+    let mut new_recovery_key = use_signal(|| None::<Zeroizing<String>>); // يُعرض مرة واحدة فقط
+    let mut offer_recovery = use_signal(|| false); // عرض إنشاء عبارة الاسترداد بعد إنشاء الخزنة
+
+    // رمز التحقق الزمني: يُطلب بعد رمز الآلة الحاسبة وقبل كلمة المرور إن كان مفعلاً
+    let mut totp_passed = use_signal(|| false);
+    let mut totp_input = use_signal(String::new);
+    let mut show_totp = use_signal(|| false);
+    let mut totp_enrolling = use_signal(|| None::<TotpConfig>); // سر جديد لم يُؤكَّد بعد
+    let mut show_other_vault = use_signal(|| false);
+    let mut other_vault_code = use_signal(String::new);
+    let mut other_vault_message = use_signal(String::new);
+    let mut show_duress = use_signal(|| false);
+    let mut duress_code = use_signal(String::new);
+    let mut duress_mode = use_signal(|| DuressMode::ShowUnlock);
+    let mut duress_message = use_signal(String::new);
+    let mut totp_code = use_signal(String::new);
+
+    // رمز الفتح: يُختار عند التشغيل الأول ويُغيَّر من الإعدادات، ولا يُحفظ إلا مشتقاً
+    // ملف رموز تالف يبقي شاشة الإعداد مع الخطأ، ولا يُستبدل حتى يُسترجع من النسخة الاحتياطية
+    let codes_state = use_hook(|| codes_configured().map_err(|e| e.to_string()));
+    let mut codes_ready = use_signal(|| codes_state == Ok(true));
+    let mut show_unlock_code = use_signal(|| false);
+    let mut unlock_code = use_signal(String::new);
+    let mut unlock_code_confirm = use_signal(String::new);
+    let mut unlock_code_message = use_signal(|| codes_state.err().map(|e| format!("❌ {}", e)).unwrap_or_default());
+    let mut totp_message = use_signal(String::new);
+
+    let mut scrub_report = use_signal(|| None::<ScrubReport>); // نتيجة آخر فحص للسلامة
+    let mut pending_duplicates = use_signal(Vec::<PendingDuplicate>::new); // ملفات مرفوعة محتواها في الخزنة
+    let mut slot_message = use_signal(String::new);
 
     let insert = use_callback(move |txt: &str| {
         let mut s = input();
@@ -72,25 +145,51 @@ fn app() -> Element {
         }
     });
 
+    // عرض شاشة خزنة: إعدادات القفل تُقرأ من ترويستها مرة واحدة هنا لا مع كل فحص للخمول
+    let mut show_vault_screen = move |vault_dir: PathBuf, attempts: AttemptsKey| {
+        attempts_key.set(Some(attempts));
+        let header = VaultHeader::load(&vault_dir);
+        auto_lock_minutes.set(header.as_ref().map_or(DEFAULT_AUTO_LOCK_MINUTES, |h| h.auto_lock_minutes));
+        lock_on_blur.set(header.is_some_and(|h| h.lock_on_blur));
+        select_vault(vault_dir);
+        show_vault.set(true);
+    };
+
     let calculate = use_callback(move |_| {
-        let expr = input().replace('×', "*").replace('÷', "/");
-        if expr.trim().is_empty() {
+        let expr = normalize_expression(&input());
+        if expr.is_empty() {
             result.set(String::new());
             return;
         }
         match eval_str(&expr) {
             Ok(v) => {
-                let out = if v.fract().abs() < 1e-12 {
-                    format!("{}", v.round() as i64)
-                } else {
-                    let s = format!("{:.12}", v);
-                    s.trim_end_matches('0').trim_end_matches('.').to_string()
-                };
+                let out = format_result(v);
                 result.set(out.clone());
-                
-                if out == "49" {
-                    show_vault.set(true);
-                }
+
+                // الرمز قد يكون النتيجة أو العملية كاملة - الصيغتان تُقارنان دائماً بزمن ثابت
+                // فلا يختلف الزمن بين رمز الفتح ورمز الإتلاف وأي عملية عادية. الاشتقاق بطيء
+                // عمداً فيجري خارج خيط الواجهة، والنتيجة ظاهرة قبله
+                let candidates = [format!("expr:{}", expr), format!("value:{}", out)];
+                spawn(async move {
+                    let found = tokio::task::spawn_blocking(move || {
+                        let found = match_code(&[&candidates[0], &candidates[1]]);
+                        // رمز الإتلاف يمحو مفاتيح خزنته قبل أي شيء آخر
+                        if let CodeMatch::Duress(duress, _) = &found {
+                            let _ = destroy_vault(&duress.vault_dir);
+                        }
+                        found
+                    }).await;
+                    match found {
+                        // بعد الإتلاف يتصرف كفتح عادي أو كنتيجة عادية
+                        Ok(CodeMatch::Duress(duress, attempts)) if duress.mode == DuressMode::ShowUnlock => {
+                            show_vault_screen(duress.vault_dir, attempts);
+                        }
+                        Ok(CodeMatch::Unlock(attempts)) => show_vault_screen(primary_vault_dir(), attempts),
+                        // كل رمز يفتح خزنته فقط - ولا فرق ظاهر بين الرموز
+                        Ok(CodeMatch::Vault(vault_dir, attempts)) => show_vault_screen(vault_dir, attempts),
+                        _ => {}
+                    }
+                });
             }
             Err(_) => result.set("خطأ في الصيغة".to_string()),
         }
@@ -122,9 +221,510 @@ fn app() -> Element {
         }
     };
 
+    // ✅ ترحيل الملفات المشفرة بالمفتاح الثابت القديم - يُستأنف عند كل فتح حتى يكتمل
+    let migrate_legacy = move |key: SecretKey| {
+        let generation = *lock_generation.peek();
+        spawn(async move {
+            let pending: Vec<String> = encrypted_files()
+                .iter()
+                .filter(|f| f.scheme == KeyScheme::Legacy)
+                .map(|f| f.encrypted_path.clone())
+                .collect();
+            if pending.is_empty() {
+                return;
+            }
+
+            let mut failed = 0;
+            for (done, encrypted_path) in pending.iter().enumerate() {
+                migration_message.set(format!("🔄 ترحيل الملفات القديمة: {}/{}", done + 1, pending.len()));
+                tokio::task::yield_now().await;
+                // أُقفلت الخزنة أثناء الانتظار: يُستأنف عند الفتح التالي
+                if *lock_generation.peek() != generation {
+                    return;
+                }
+
+                let mut files = encrypted_files();
+                let Some(position) = files.iter().position(|f| &f.encrypted_path == encrypted_path) else {
+                    continue;
+                };
+                let old = files[position].clone();
+                match migrate_legacy_file(&old, &key) {
+                    Ok(migrated) => {
+                        // الفهرس يُحفظ بعد كل ملف حتى يُستأنف الترحيل من حيث توقف
+                        let blob = migrated.encrypted_path.clone();
+                        files[position] = migrated;
+                        if save_encrypted_files(&files, &key).is_err() {
+                            // الفهرس ما زال يشير إلى الملف القديم - يبقى كما هو والحاوية الجديدة تُحذف
+                            let _ = fs::remove_file(&blob);
+                            failed += 1;
+                            continue;
+                        }
+                        encrypted_files.set(files);
+                        // الصيغة القديمة بمفتاح ثابت معروف - حكمها حكم النص الأصلي
+                        let _ = shred_file(Path::new(&old.encrypted_path));
+                    }
+                    Err(e) => {
+                        // السبب يُحفظ مع الإدخال في الفهرس المشفر فيبقى ظاهراً بجانب الملف حتى ينجح ترحيله
+                        files[position].migration_error = Some(e.to_string());
+                        let _ = save_encrypted_files(&files, &key);
+                        encrypted_files.set(files);
+                        failed += 1;
+                    }
+                }
+            }
+
+            if failed == 0 {
+                migration_message.set(format!("✅ تم ترحيل {} ملف إلى المفتاح الجديد", pending.len()));
+            } else {
+                migration_message.set(format!(
+                    "⚠️ تعذر ترحيل {} ملف - السبب معروض بجانب كل ملف، وتُعاد المحاولة عند الفتح التالي",
+                    failed
+                ));
+            }
+        });
+    };
+
+    // ✅ التحقق من رمز TOTP محلياً بساعة الجهاز - لا حاجة لاتصال
+    let submit_totp = use_callback(move |_| {
+        let vault_dir = get_vault_dir();
+        let Some(attempts) = attempts_key() else { return };
+        if let Some(wait) = unlock_delay(&attempts) {
+            auth_error.set(wait_message(wait));
+            return;
+        }
+        let Some(mut header) = VaultHeader::load(&vault_dir) else { return };
+        let Some(mut totp) = header.totp.take() else { return };
+
+        let code = totp_input();
+        totp_input.set(String::new());
+        if totp.verify(&code) {
+            header.totp = Some(totp);
+            if header.save(&vault_dir).is_ok() {
+                totp_passed.set(true);
+                auth_error.set(String::new());
+            }
+        } else {
+            failed_unlock(&vault_dir, &attempts);
+            auth_error.set("رمز التحقق غير صحيح".to_string());
+        }
+    });
+
+    // ما يلي أي فتح ناجح للخزنة، بكلمة المرور أو بعبارة الاسترداد
+    let mut on_unlocked = move |key: SecretKey| {
+        core_dumps.set(Some(suppress_core_dumps()));
+        vault_key.set(Some(key.clone()));
+        password_input.write().zeroize();
+        password_confirm.write().zeroize();
+        recovery_phrase.write().zeroize();
+        keyfile_path.set(String::new());
+        recovering.set(false);
+        auth_error.set(String::new());
+        // المحاولات الفاشلة تُذكر لصاحب الخزنة فقط، بعد فتحها
+        let failures = attempts_key().map_or(0, |attempts| reset_failed_unlocks(&attempts));
+        if failures > 0 {
+            change_message.set(format!("⚠️ {} محاولات فتح فاشلة منذ آخر فتح", failures));
+        }
+        if let Some(header) = VaultHeader::load(&get_vault_dir()) {
+            padding_policy.set(header.padding);
+            compression.set(header.compression);
+            shred_passes.set(header.shred_passes);
+            wipe_after.set(header.wipe_after);
+            auto_lock_minutes.set(header.auto_lock_minutes);
+            lock_on_blur.set(header.lock_on_blur);
+        }
+        load_encrypted_files(&mut encrypted_files, &key, &mut upload_message);
+        migrate_legacy(key);
+    };
+
+    // ✅ إنشاء الخزنة أول مرة أو فتحها بكلمة المرور
+    let submit_password = use_callback(move |_| {
+        let vault_dir = get_vault_dir();
+        let password = Zeroizing::new(password_input());
+        let keyfile = PathBuf::from(keyfile_path());
+        let creating = VaultHeader::load(&vault_dir).is_none();
+        let Some(attempts) = attempts_key() else { return };
+        if let Some(wait) = unlock_delay(&attempts).filter(|_| !creating) {
+            auth_error.set(wait_message(wait));
+            return;
+        }
+
+        let unlocked = match VaultHeader::load(&vault_dir) {
+            // حقل كلمة المرور فارغ ومسار ملف مفتاح معطى: الفتح بالملف
+            Some(_) if password.is_empty() && !keyfile.as_os_str().is_empty() => {
+                open_vault(&vault_dir, &Credential::Keyfile(&keyfile))
+            }
+            Some(_) => open_vault(&vault_dir, &Credential::Secret(&password)),
+            None => {
+                if password.chars().count() < 8 {
+                    auth_error.set("كلمة المرور يجب أن تكون 8 أحرف على الأقل".to_string());
+                    return;
+                }
+                if *password != *password_confirm.read() {
+                    auth_error.set("كلمتا المرور غير متطابقتين".to_string());
+                    return;
+                }
+                VaultHeader::create(&vault_dir, &password, vault_cipher())
+            }
+        };
+
+        match unlocked {
+            Ok(key) => {
+                offer_recovery.set(creating);
+                on_unlocked(key);
+            }
+            Err(e) => {
+                failed_unlock(&vault_dir, &attempts);
+                auth_error.set(e.to_string());
+            }
+        }
+    });
+
+    // ✅ الاسترداد: إثبات امتلاك العبارة ثم تعيين كلمة مرور جديدة
+    let submit_recovery = use_callback(move |_| {
+        let password = Zeroizing::new(password_input());
+        if password.chars().count() < 8 {
+            auth_error.set("كلمة المرور يجب أن تكون 8 أحرف على الأقل".to_string());
+            return;
+        }
+        if *password != *password_confirm.read() {
+            auth_error.set("كلمتا المرور غير متطابقتين".to_string());
+            return;
+        }
+        let vault_dir = get_vault_dir();
+        let Some(attempts) = attempts_key() else { return };
+        if let Some(wait) = unlock_delay(&attempts) {
+            auth_error.set(wait_message(wait));
+            return;
+        }
+        let phrase = Zeroizing::new(recovery_phrase());
+        match recover_vault(&vault_dir, &phrase, &password) {
+            Ok(key) => {
+                on_unlocked(key);
+                change_message.set("✅ تم تعيين كلمة مرور جديدة - يمكنك حذف المنسية من مفاتيح الخزنة".to_string());
+            }
+            Err(e) => {
+                failed_unlock(&vault_dir, &attempts);
+                auth_error.set(e.to_string());
+            }
+        }
+    });
+
+    // ✅ تغيير كلمة المرور وتدوير المفتاح الرئيسي
+    let submit_change_password = move |_| {
+        if *new_password.read() != *new_password_confirm.read() {
+            change_message.set("❌ كلمتا المرور غير متطابقتين".to_string());
+            return;
+        }
+        if new_password.read().chars().count() < 8 {
+            change_message.set("❌ كلمة المرور يجب أن تكون 8 أحرف على الأقل".to_string());
+            return;
+        }
+        let result = change_password(&get_vault_dir(), &old_password.read(), &new_password.read());
+        match result {
+            Ok(key) => {
+                load_encrypted_files(&mut encrypted_files, &key, &mut upload_message);
+                vault_key.set(Some(key));
+                old_password.write().zeroize();
+                new_password.write().zeroize();
+                new_password_confirm.write().zeroize();
+                show_change_password.set(false);
+                change_message.set("✅ تم تغيير كلمة المرور".to_string());
+            }
+            Err(e) => change_message.set(format!("❌ {}", e)),
+        }
+    };
+
+    let close_vault = use_callback(move |_| {
+        show_vault.set(false);
+        select_vault(primary_vault_dir());
+        // إسقاط المفتاح يمسحه من الذاكرة، ونسخ المهام الجارية تُسقط مع توقفها
+        vault_key.set(None);
+        attempts_key.set(None);
+        *lock_generation.write() += 1;
+        // القائمة المفكوكة لا تبقى في الذاكرة بعد الإغلاق
+        encrypted_files.set(Vec::new());
+        selected_file_index.set(None);
+        upload_message.set(String::new());
+        migration_message.set(String::new());
+        password_input.write().zeroize();
+        password_confirm.write().zeroize();
+        old_password.write().zeroize();
+        new_password.write().zeroize();
+        new_password_confirm.write().zeroize();
+        auth_error.set(String::new());
+        show_change_password.set(false);
+        change_message.set(String::new());
+        show_key_slots.set(false);
+        new_slot_secret.write().zeroize();
+        new_slot_confirm.write().zeroize();
+        new_recovery_key.set(None);
+        offer_recovery.set(false);
+        recovery_phrase.write().zeroize();
+        recovering.set(false);
+        slot_message.set(String::new());
+        totp_passed.set(false);
+        totp_input.set(String::new());
+        show_totp.set(false);
+        show_other_vault.set(false);
+        other_vault_code.set(String::new());
+        other_vault_message.set(String::new());
+        show_duress.set(false);
+        duress_code.set(String::new());
+        duress_message.set(String::new());
+        show_unlock_code.set(false);
+        unlock_code.write().zeroize();
+        unlock_code_confirm.write().zeroize();
+        unlock_code_message.set(String::new());
+        totp_enrolling.set(None);
+        totp_code.set(String::new());
+        totp_message.set(String::new());
+        scrub_report.set(None);
+        pending_duplicates.set(Vec::new());
+        core_dumps.set(None);
+    });
+
+    // القفل التلقائي: إغلاق كامل، ومعه شاشة الحاسبة حتى لا تبقى نتيجة الرمز ظاهرة
+    let lock_vault = use_callback(move |_| {
+        close_vault(());
+        input.set(String::new());
+        result.set(String::new());
+    });
+
+    // ✅ القفل بعد خمول - يشمل شاشة كلمة المرور فلا تبقى الخزنة ظاهرة لمن يمر.
+    // ساعة النظام لا الساعة الرتيبة حتى يُحسب وقت سكون الجهاز، وساعة أُرجعت للوراء تقفل
+    use_future(move || async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            if !*show_vault.peek() {
+                continue;
+            }
+            let minutes = *auto_lock_minutes.peek();
+            let idle = last_activity.peek().elapsed().unwrap_or(Duration::MAX);
+            if minutes > 0 && idle >= Duration::from_secs(u64::from(minutes) * 60) {
+                lock_vault(());
+            }
+        }
+    });
+
+    // ✅ القفل عند مغادرة النافذة أو تصغيرها - اختياري لأنه يشمل فتح الملفات ومجلد الرفع
+    use_wry_event_handler(move |event, _| {
+        if let Event::WindowEvent { event: WindowEvent::Focused(false), .. } = event {
+            if *show_vault.peek() && *lock_on_blur.peek() {
+                lock_vault(());
+            }
+        }
+    });
+
+    // عبارة استرداد جديدة في فتحة خاصة بها - تُعرض للمستخدم مرة واحدة ثم تُمسح من الذاكرة
+    let mut add_recovery_phrase = move |key: &[u8; 32], label: &str| {
+        let phrase = generate_recovery_key();
+        let added = add_key_slot(&get_vault_dir(), key, SlotKind::Recovery, label, None, &Credential::Secret(&phrase));
+        if added.is_ok() {
+            new_recovery_key.set(Some(phrase));
+        }
+        added
+    };
+
+    let create_recovery_phrase = move |_| {
+        let Some(key) = vault_key() else { return };
+        offer_recovery.set(false);
+        if let Err(e) = add_recovery_phrase(&key, "عبارة الاسترداد") {
+            change_message.set(format!("❌ {}", e));
+        }
+    };
+
+    // ✅ إضافة فتحة مفتاح جديدة بالمفتاح الرئيسي الموجود في الذاكرة
+    let add_slot = move |_| {
+        let Some(key) = vault_key() else { return };
+        let kind = new_slot_kind();
+        let label = new_slot_label();
+        let secret = Zeroizing::new(new_slot_secret());
+
+        let added = match kind {
+            SlotKind::Password => {
+                if secret.chars().count() < 8 {
+                    slot_message.set("❌ كلمة المرور يجب أن تكون 8 أحرف على الأقل".to_string());
+                    return;
+                }
+                if *secret != *new_slot_confirm.read() {
+                    slot_message.set("❌ كلمتا المرور غير متطابقتين".to_string());
+                    return;
+                }
+                let keyfile = PathBuf::from(new_slot_keyfile());
+                let keyfile = (!keyfile.as_os_str().is_empty()).then_some(keyfile.as_path());
+                add_key_slot(&get_vault_dir(), &key, kind, &label, keyfile, &Credential::Secret(&secret))
+            }
+            SlotKind::Recovery => add_recovery_phrase(&key, &label),
+            SlotKind::Keyfile => {
+                add_key_slot(&get_vault_dir(), &key, kind, &label, None, &Credential::Keyfile(&PathBuf::from(&*secret)))
+            }
+        };
+
+        match added {
+            Ok(()) => {
+                new_slot_label.set(String::new());
+                new_slot_keyfile.set(String::new());
+                new_slot_secret.write().zeroize();
+                new_slot_confirm.write().zeroize();
+                slot_message.set("✅ تمت إضافة المفتاح".to_string());
+            }
+            Err(e) => slot_message.set(format!("❌ {}", e)),
+        }
+    };
+
+    let mut remove_slot = move |index: usize| {
+        match remove_key_slot(&get_vault_dir(), index) {
+            Ok(()) => slot_message.set("✅ تم حذف المفتاح".to_string()),
+            Err(e) => slot_message.set(format!("❌ {}", e)),
+        }
+    };
+
+    // ✅ تفعيل رمز التحقق: السر يُحفظ فقط بعد إدخال رمز صحيح منه
+    let confirm_totp = move |_| {
+        let Some(mut totp) = totp_enrolling() else { return };
+        if !totp.verify(&totp_code()) {
+            totp_message.set("❌ رمز التحقق غير صحيح".to_string());
+            return;
+        }
+        let vault_dir = get_vault_dir();
+        let Some(mut header) = VaultHeader::load(&vault_dir) else { return };
+        header.totp = Some(totp);
+        match header.save(&vault_dir) {
+            Ok(()) => {
+                totp_enrolling.set(None);
+                totp_code.set(String::new());
+                totp_message.set("✅ تم تفعيل رمز التحقق".to_string());
+            }
+            Err(e) => totp_message.set(format!("❌ {}", e)),
+        }
+    };
+
+    let disable_totp = move |_| {
+        let vault_dir = get_vault_dir();
+        let Some(mut header) = VaultHeader::load(&vault_dir) else { return };
+        header.totp = None;
+        match header.save(&vault_dir) {
+            Ok(()) => totp_message.set("✅ تم إيقاف رمز التحقق".to_string()),
+            Err(e) => totp_message.set(format!("❌ {}", e)),
+        }
+    };
+
+    // ✅ فحص السلامة: فك كل ملف دون كتابته ومقارنة مختصره، ثم البحث عن الإدخالات المعلقة والملفات اليتيمة
+    let run_scrub = move |_| {
+        spawn(async move {
+            let Some(key) = vault_key() else { return };
+            let generation = *lock_generation.peek();
+            let files = encrypted_files();
+            let mut report = ScrubReport::default();
+            // مختصرات الإدخالات الأقدم التي لم تحمل مختصراً بعد - تُحفظ بعد أول فحص ناجح
+            let mut computed = HashMap::new();
+
+            for (done, file) in files.iter().enumerate() {
+                upload_message.set(format!("🩺 فحص الملفات: {}/{}", done + 1, files.len()));
+                tokio::task::yield_now().await;
+                if *lock_generation.peek() != generation {
+                    return;
+                }
+
+                if !Path::new(&file.encrypted_path).exists() {
+                    report.missing.push((file.encrypted_path.clone(), file.name.clone()));
+                    continue;
+                }
+                report.checked += 1;
+                let mut sink = HashingSink::default();
+                match decrypt_file(file, &key, &mut sink) {
+                    Ok(()) => {
+                        let digest = sink.finish();
+                        if file.sha256.is_empty() {
+                            computed.insert(file.encrypted_path.clone(), digest);
+                        } else if file.sha256 != digest {
+                            report.damaged.push((
+                                file.encrypted_path.clone(),
+                                file.name.clone(),
+                                "المحتوى لا يطابق المختصر المحفوظ".to_string(),
+                            ));
+                        }
+                    }
+                    Err(e) => report.damaged.push((file.encrypted_path.clone(), file.name.clone(), e.to_string())),
+                }
+            }
+
+            // القائمة قد تكون تغيرت أثناء الفحص - نحدّث الإدخالات الحالية لا النسخة القديمة
+            let mut files = encrypted_files();
+            if !computed.is_empty() {
+                for file in files.iter_mut().filter(|f| f.sha256.is_empty()) {
+                    if let Some(digest) = computed.get(&file.encrypted_path) {
+                        file.sha256 = digest.clone();
+                        file.content_mac = content_mac(&key, digest);
+                    }
+                }
+                if let Err(e) = save_encrypted_files(&files, &key) {
+                    upload_message.set(format!("❌ تعذر حفظ الفهرس: {}", e));
+                    return;
+                }
+                encrypted_files.set(files.clone());
+            }
+            report.orphans = find_orphans(&get_vault_dir(), &files).unwrap_or_default();
+
+            upload_message.set(if report.is_healthy() {
+                format!("✅ الخزنة سليمة - تم فحص {} ملف", report.checked)
+            } else {
+                "⚠️ الفحص وجد مشكلات - التفاصيل في التقرير".to_string()
+            });
+            scrub_report.set(Some(report));
+        });
+    };
+
+    // ✅ معالجة نتيجة الفحص: الملفات التالفة واليتيمة تُنقل إلى الحجر أو تُحذف،
+    // والإدخالات التالفة أو المعلقة تُزال من الفهرس
+    let mut resolve_scrub = move |delete: bool| {
+        let Some(key) = vault_key() else { return };
+        let Some(report) = scrub_report() else { return };
+        let vault_dir = get_vault_dir();
+
+        // الإدخالات المرتبطة تشترك في حاوية واحدة فقد تتكرر في التقرير
+        // الفهرس يُحفظ أولاً - إن تعذر حفظه تبقى الحاويات كما هي ولا يشير الفهرس إلى ملف محذوف
+        let broken: HashSet<&String> = report.damaged.iter().map(|(path, _, _)| path)
+            .chain(report.missing.iter().map(|(path, _)| path))
+            .collect();
+        let mut files = encrypted_files();
+        files.retain(|f| !broken.contains(&f.encrypted_path));
+        if let Err(e) = save_encrypted_files(&files, &key) {
+            upload_message.set(format!("❌ تعذر حفظ الفهرس: {}", e));
+            return;
+        }
+        encrypted_files.set(files);
+        selected_file_index.set(None);
+        scrub_report.set(None);
+
+        let blobs: HashSet<PathBuf> = report.damaged.iter().map(|(path, _, _)| PathBuf::from(path))
+            .chain(report.orphans.iter().cloned())
+            .collect();
+        let mut failed = 0;
+        let mut caveat = None;
+        for path in blobs {
+            let result = if delete {
+                shred_file(&path).map(|warning| caveat = caveat.take().or(warning))
+            } else {
+                quarantine(&vault_dir, &path)
+            };
+            if result.is_err() {
+                failed += 1;
+            }
+        }
+
+        upload_message.set(with_caveat(match (failed, delete) {
+            (0, true) => "✅ تم حذف الملفات التالفة واليتيمة".to_string(),
+            (0, false) => format!("✅ نُقلت الملفات التالفة واليتيمة إلى:\n{}", vault_dir.join("quarantine").display()),
+            (n, _) => format!("⚠️ تعذرت معالجة {} ملف", n),
+        }, caveat));
+    };
+
     // ✅ دالة رفع الملفات - بدون مربع حوار - مراقبة مجلد
     let upload_file = move |_| {
         spawn(async move {
+            let Some(key) = vault_key() else { return };
+            let generation = *lock_generation.peek();
             let vault_dir = get_vault_dir();
             let upload_dir = vault_dir.join("upload");
             
@@ -135,77 +735,647 @@ fn app() -> Element {
                 // فحص الملفات في المجلد
                 if let Ok(entries) = fs::read_dir(&upload_dir) {
                     for entry in entries.flatten() {
+                        if *lock_generation.peek() != generation {
+                            return;
+                        }
                         if let Ok(metadata) = entry.metadata() {
                             if metadata.is_file() {
                                 if let Some(file_name) = entry.file_name().to_str() {
-                                    if let Ok(data) = fs::read(entry.path()) {
-                                        // تشفير الملف
-                                        if let Ok(encrypted_info) = encrypt_file(file_name, &data) {
-                                            let mut files = encrypted_files();
-                                            files.push(encrypted_info);
-                                            save_encrypted_files(&files);
-                                            encrypted_files.set(files);
-                                            
-                                            // حذف الملف الأصلي بعد التشفير
-                                            let _ = fs::remove_file(entry.path());
-                                            upload_message.set(format!("✅ تم تشفير: {}", file_name));
+                                    let path = entry.path();
+                                    // ملف محتواه موجود في الخزنة ينتظر قرار المستخدم بدل تشفيره مرة ثانية
+                                    let Ok(sha256) = hash_file(&path) else { continue };
+                                    let mac = content_mac(&key, &sha256);
+                                    if let Some(existing) = encrypted_files().iter().find(|f| f.content_mac == mac) {
+                                        if !pending_duplicates().iter().any(|d| d.source == path) {
+                                            pending_duplicates.write().push(PendingDuplicate {
+                                                source: path,
+                                                name: file_name.to_string(),
+                                                existing: existing.name.clone(),
+                                                content_mac: mac,
+                                            });
                                         }
+                                        continue;
+                                    }
+                                    if let Ok(caveat) = import_upload(&path, file_name, &key, &mut encrypted_files) {
+                                        upload_message.set(with_caveat(format!("✅ تم تشفير: {}", file_name), caveat));
                                     }
                                 }
                             }
                         }
                     }
                 }
-            }
-        });
-    };
+            }
+        });
+    };
+
+    let decrypt_and_open = move |index: usize| {
+        spawn(async move {
+            let Some(key) = vault_key() else { return };
+            let files = encrypted_files();
+            if let Some(file) = files.get(index) {
+                let temp_dir = std::env::temp_dir();
+                let temp_path = temp_dir.join(&file.name);
+                if let Ok(output) = fs::File::create(&temp_path) {
+                    // الكتابة مباشرة في الملف: القطع كبيرة، ولا نريد نسخة من النص في مخزن وسيط
+                    match decrypt_file(file, &key, output) {
+                        Ok(()) => {
+                            let _ = open::that(&temp_path);
+                        }
+                        // لا نترك نصاً مفكوكاً جزئياً من ملف تالف
+                        Err(_) => {
+                            let _ = shred_file(&temp_path);
+                        }
+                    }
+                }
+            }
+        });
+    };
+
+    // ✅ قرار المستخدم لأول ملف مكرر في الانتظار
+    let mut resolve_duplicate = move |action: DuplicateAction| {
+        let Some(key) = vault_key() else { return };
+        let Some(duplicate) = pending_duplicates().first().cloned() else { return };
+        pending_duplicates.write().remove(0);
+
+        let result = match action {
+            // المحتوى موجود في الخزنة - النسخة المرفوعة تُحذف دون تشفير
+            DuplicateAction::Skip => shred_file(&duplicate.source).map_err(Into::into),
+            DuplicateAction::Link => link_upload(&duplicate, &key, &mut encrypted_files),
+            DuplicateAction::Keep => import_upload(&duplicate.source, &duplicate.name, &key, &mut encrypted_files),
+        };
+        upload_message.set(match (result, action) {
+            (Ok(caveat), DuplicateAction::Skip) => with_caveat(format!("⏭️ تم تخطي: {}", duplicate.name), caveat),
+            (Ok(caveat), DuplicateAction::Link) => with_caveat(format!("🔗 تم ربط {} بالنسخة الموجودة", duplicate.name), caveat),
+            (Ok(caveat), DuplicateAction::Keep) => with_caveat(format!("✅ تم تشفير نسخة مستقلة: {}", duplicate.name), caveat),
+            (Err(e), _) => format!("❌ تعذرت معالجة {}: {}", duplicate.name, e),
+        });
+    };
+
+    let mut delete_file = move |index: usize| {
+        let Some(key) = vault_key() else { return };
+        let mut files = encrypted_files();
+        if index < files.len() {
+            // الحاوية المشتركة بين إدخالات مرتبطة تُحذف مع آخر إدخال يشير إليها
+            let file = files.remove(index);
+            let shared = files.iter().any(|f| f.encrypted_path == file.encrypted_path);
+            // الحاوية لا تُحذف قبل أن يُحفظ فهرس لا يشير إليها
+            if let Err(e) = save_encrypted_files(&files, &key) {
+                upload_message.set(format!("❌ تعذر حفظ الفهرس: {}", e));
+                return;
+            }
+            encrypted_files.set(files);
+            selected_file_index.set(None);
+            if shared {
+                upload_message.set(format!("🗑️ تم حذف: {}", file.name));
+            } else {
+                match shred_file(Path::new(&file.encrypted_path)) {
+                    Ok(caveat) => upload_message.set(with_caveat(format!("🗑️ تم حذف: {}", file.name), caveat)),
+                    Err(e) => upload_message.set(format!("⚠️ تعذر الحذف الآمن للملف المشفر: {}", e)),
+                }
+            }
+        }
+    };
+
+    let mut set_shred_passes = move |passes: u32| {
+        let vault_dir = get_vault_dir();
+        if let Some(mut header) = VaultHeader::load(&vault_dir) {
+            header.shred_passes = passes;
+            if header.save(&vault_dir).is_ok() {
+                shred_passes.set(passes);
+            }
+        }
+    };
+
+    let mut set_auto_lock = move |minutes: u32| {
+        let vault_dir = get_vault_dir();
+        if let Some(mut header) = VaultHeader::load(&vault_dir) {
+            header.auto_lock_minutes = minutes;
+            if header.save(&vault_dir).is_ok() {
+                auto_lock_minutes.set(minutes);
+            }
+        }
+    };
+
+    let toggle_lock_on_blur = move |_| {
+        let vault_dir = get_vault_dir();
+        if let Some(mut header) = VaultHeader::load(&vault_dir) {
+            header.lock_on_blur = !header.lock_on_blur;
+            if header.save(&vault_dir).is_ok() {
+                lock_on_blur.set(header.lock_on_blur);
+            }
+        }
+    };
+
+    let mut set_wipe_after = move |limit: Option<u32>| {
+        let vault_dir = get_vault_dir();
+        if let Some(mut header) = VaultHeader::load(&vault_dir) {
+            header.wipe_after = limit;
+            if header.save(&vault_dir).is_ok() {
+                wipe_after.set(limit);
+            }
+        }
+    };
+
+    // سياسة الحشو تُطبق على الملفات المرفوعة بعد تغييرها فقط
+    let mut set_padding = move |policy: PaddingPolicy| {
+        let vault_dir = get_vault_dir();
+        if let Some(mut header) = VaultHeader::load(&vault_dir) {
+            header.padding = policy;
+            if header.save(&vault_dir).is_ok() {
+                padding_policy.set(policy);
+            }
+        }
+    };
+
+    let toggle_compression = move |_| {
+        let vault_dir = get_vault_dir();
+        if let Some(mut header) = VaultHeader::load(&vault_dir) {
+            header.compression = !header.compression;
+            if header.save(&vault_dir).is_ok() {
+                compression.set(header.compression);
+            }
+        }
+    };
+
+    // ✅ خزنة أخرى تُفتح برمز حسابي آخر: تُغلق الحالية وتظهر شاشة إنشاء الجديدة في جذرها
+    let create_other_vault = move |_| {
+        let code = match code_from_setting(&other_vault_code()) {
+            Ok(code) => code,
+            Err(e) => {
+                other_vault_message.set(format!("❌ {}", e));
+                return;
+            }
+        };
+        if !matches!(match_code(&[&code]), CodeMatch::None) {
+            other_vault_message.set("❌ هذا الرمز مستخدم - اختر رمزاً آخر".to_string());
+            return;
+        }
+        let (vault_dir, attempts) = match code_vault_dir(&code) {
+            Ok(found) => found,
+            Err(e) => {
+                other_vault_message.set(format!("❌ {}", e));
+                return;
+            }
+        };
+        close_vault(());
+        show_vault_screen(vault_dir, attempts);
+    };
+
+    // ✅ رمز الإتلاف لهذه الخزنة: يُحفظ مشتقاً فقط في جدول لا يكشف وجوده
+    let save_duress_code = move |_| {
+        let code = match code_from_setting(&duress_code()) {
+            Ok(code) => code,
+            Err(e) => {
+                duress_message.set(format!("❌ {}", e));
+                return;
+            }
+        };
+        if matches!(match_code(&[&code]), CodeMatch::Unlock(_) | CodeMatch::Vault(..)) {
+            duress_message.set("❌ هذا الرمز يفتح خزنة - اختر رمزاً آخر".to_string());
+            return;
+        }
+        let Some(key) = vault_key() else { return };
+        duress_message.set(match set_duress_code(&code, &get_vault_dir(), &key, duress_mode()) {
+            Ok(()) => {
+                duress_code.set(String::new());
+                format!("✅ تم ضبط رمز الإتلاف: {}", describe_code(&code))
+            }
+            Err(e) => format!("❌ {}", e),
+        });
+    };
+
+    // ✅ رمز الفتح: عند التشغيل الأول، ومن الإعدادات بعدها. الرمز القديم يتوقف فوراً
+    let submit_unlock_code = use_callback(move |_| {
+        let code = match code_from_setting(&unlock_code()) {
+            Ok(code) => code,
+            Err(e) => {
+                unlock_code_message.set(format!("❌ {}", e));
+                return;
+            }
+        };
+        if *unlock_code.read() != *unlock_code_confirm.read() {
+            unlock_code_message.set("❌ الرمزان غير متطابقين".to_string());
+            return;
+        }
+        if matches!(match_code(&[&code]), CodeMatch::Vault(..) | CodeMatch::Duress(..)) {
+            unlock_code_message.set("❌ هذا الرمز مستخدم - اختر رمزاً آخر".to_string());
+            return;
+        }
+        match set_unlock_code(&code) {
+            Ok(()) => {
+                unlock_code_message.set(format!("✅ رمز الفتح الآن: {}", describe_code(&code)));
+                unlock_code.write().zeroize();
+                unlock_code_confirm.write().zeroize();
+                codes_ready.set(true);
+            }
+            Err(e) => unlock_code_message.set(format!("❌ {}", e)),
+        }
+    });
+
+    let remove_duress_code = move |_| {
+        let Some(key) = vault_key() else { return };
+        duress_message.set(match clear_duress_code(&get_vault_dir(), &key) {
+            Ok(()) => "✅ أُزيل رمز الإتلاف لهذه الخزنة".to_string(),
+            Err(e) => format!("❌ {}", e),
+        });
+    };
+
+    // دالة فتح مجلد الرفع
+    let open_upload_folder = move |_| {
+        let vault_dir = get_vault_dir();
+        let upload_dir = vault_dir.join("upload");
+        let _ = fs::create_dir_all(&upload_dir);
+        let _ = open::that(&upload_dir);
+    };
+
+    let totp_required = VaultHeader::load(&get_vault_dir()).is_some_and(|h| h.totp.is_some());
+
+    let view = if !codes_ready() {
+        render_code_setup(unlock_code, unlock_code_confirm, unlock_code_message, submit_unlock_code)
+    } else if show_vault() && vault_key().is_none() && totp_required && !totp_passed() {
+        render_totp_prompt(totp_input, auth_error, submit_totp, close_vault)
+    } else if show_vault() && vault_key().is_none() {
+        let form = AuthForm {
+            password: password_input,
+            confirm: password_confirm,
+            keyfile: keyfile_path,
+            recovery_phrase,
+            recovering,
+            error: auth_error,
+        };
+        render_vault_auth(form, vault_cipher, submit_password, submit_recovery, close_vault)
+    } else if show_vault() {
+        let key_slots = VaultHeader::load(&get_vault_dir()).map(|h| h.slots).unwrap_or_default();
+        rsx! {
+            div { 
+                style: "min-height:100vh;background:linear-gradient(135deg,#667eea 0%,#764ba2 100%);color:white;padding:20px;font-family:system-ui,sans-serif;",
+                
+                div { 
+                    style: "max-width:800px;margin:0 auto;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;margin-bottom:20px;display:flex;justify-content:space-between;align-items:center;",
+                    h2 { "🔐 الخزنة السرية" }
+                    div { style: "display:flex;gap:10px;",
+                        button {
+                            style: "background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: move |_| show_change_password.set(!show_change_password()),
+                            "🔑 تغيير كلمة المرور"
+                        }
+                        button {
+                            style: "background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: move |_| {
+                                show_key_slots.set(!show_key_slots());
+                                new_recovery_key.set(None);
+                                slot_message.set(String::new());
+                            },
+                            "🗝️ مفاتيح الخزنة"
+                        }
+                        button {
+                            style: "background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: move |_| {
+                                show_totp.set(!show_totp());
+                                totp_enrolling.set(None);
+                                totp_message.set(String::new());
+                            },
+                            "📱 رمز التحقق"
+                        }
+                        button {
+                            style: "background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: run_scrub,
+                            "🩺 فحص الخزنة"
+                        }
+                        button {
+                            style: "background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: move |_| {
+                                show_other_vault.set(!show_other_vault());
+                                other_vault_message.set(String::new());
+                            },
+                            "🎭 خزنة أخرى"
+                        }
+                        button {
+                            style: "background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: move |_| {
+                                show_duress.set(!show_duress());
+                                duress_message.set(String::new());
+                            },
+                            "🧨 رمز الإتلاف"
+                        }
+                        // رمز الفتح يخص الخزنة الأصلية فقط - الخزنات الأخرى تُفتح برموزها
+                        if get_vault_dir() == primary_vault_dir() {
+                            button {
+                                style: "background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                                onclick: move |_| {
+                                    show_unlock_code.set(!show_unlock_code());
+                                    unlock_code_message.set(String::new());
+                                },
+                                "🔢 رمز الفتح"
+                            }
+                        }
+                        button {
+                            style: "background:#f5576c;border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: move |_| close_vault(()),
+                            "إغلاق"
+                        }
+                    }
+                }
+
+                // تغيير رمز الفتح
+                if show_unlock_code() {
+                    div {
+                        style: "max-width:800px;margin:0 auto 20px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;display:flex;flex-direction:column;gap:10px;",
+                        div { style: "font-size:14px;opacity:0.9;",
+                            "رقم يفتح الخزنة مع أي عملية نتيجتها هذا الرقم، أو عملية كاملة مثل 1337*3 لا تفتح إلا بكتابتها كما هي ثم =. "
+                            "الرمز الحالي يتوقف فوراً، ولا يُحفظ الجديد إلا مشتقاً."
+                        }
+                        input {
+                            style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;direction:ltr;",
+                            r#type: "password",
+                            placeholder: "رمز الفتح الجديد",
+                            value: "{unlock_code}",
+                            oninput: move |e| unlock_code.set(e.value()),
+                        }
+                        input {
+                            style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;direction:ltr;",
+                            r#type: "password",
+                            placeholder: "تأكيد الرمز",
+                            value: "{unlock_code_confirm}",
+                            oninput: move |e| unlock_code_confirm.set(e.value()),
+                        }
+                        button {
+                            style: "background:#4facfe;border:none;border-radius:12px;padding:12px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: move |_| submit_unlock_code(()),
+                            "حفظ رمز الفتح"
+                        }
+                        if !unlock_code_message().is_empty() {
+                            div { style: "text-align:center;font-size:14px;", "{unlock_code_message}" }
+                        }
+                    }
+                }
+
+                // رمز الإتلاف
+                if show_duress() {
+                    div {
+                        style: "max-width:800px;margin:0 auto 20px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;display:flex;flex-direction:column;gap:10px;",
+                        div { style: "font-size:14px;opacity:0.9;",
+                            "نتيجة أو عملية حسابية تُتلف مفاتيح هذه الخزنة فوراً دون أي إشارة ظاهرة، فلا يُفتح أي ملف فيها بعدها أبداً. "
+                            "الرمز لا يُحفظ إلا مشتقاً، ولا يُعرض هنا إن كان مضبوطاً."
+                        }
+                        input {
+                            style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;direction:ltr;",
+                            placeholder: "رمز الإتلاف (مثلاً 2024 أو 8*253)",
+                            value: "{duress_code}",
+                            oninput: move |e| duress_code.set(e.value()),
+                        }
+                        div { style: "display:flex;gap:8px;font-size:14px;",
+                            for (mode, label) in [(DuressMode::ShowUnlock, "بعده: شاشة كلمة المرور كالمعتاد"), (DuressMode::StayOnCalculator, "بعده: لا شيء كرمز خاطئ")] {
+                                button {
+                                    key: "{label}",
+                                    style: if duress_mode() == mode { "flex:1;background:rgba(255,255,255,0.35);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" } else { "flex:1;background:rgba(255,255,255,0.1);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" },
+                                    onclick: move |_| duress_mode.set(mode),
+                                    "{label}"
+                                }
+                            }
+                        }
+                        div { style: "display:flex;gap:10px;",
+                            button {
+                                style: "flex:1;background:#f5576c;border:none;border-radius:12px;padding:12px;color:white;font-weight:700;cursor:pointer;",
+                                onclick: save_duress_code,
+                                "حفظ رمز الإتلاف"
+                            }
+                            button {
+                                style: "flex:1;background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px;color:white;cursor:pointer;",
+                                onclick: remove_duress_code,
+                                "إزالة رمز الإتلاف"
+                            }
+                        }
+                        if !duress_message().is_empty() {
+                            div { style: "text-align:center;font-size:14px;", "{duress_message}" }
+                        }
+                    }
+                }
+
+                // إنشاء خزنة أخرى برمز مختلف
+                if show_other_vault() {
+                    div {
+                        style: "max-width:800px;margin:0 auto 20px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;display:flex;flex-direction:column;gap:10px;",
+                        div { style: "font-size:14px;opacity:0.9;",
+                            "خزنة مستقلة بملفاتها وكلمة مرورها، تُفتح برمز آخر غير رمز الفتح: نتيجة أو عملية كاملة. "
+                            "الرمز لا يُحفظ في أي مكان، ولا تشير أي من الخزنتين إلى الأخرى - احفظه جيداً."
+                        }
+                        input {
+                            style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;direction:ltr;",
+                            placeholder: "الرمز (مثلاً 1337 أو 7*191)",
+                            value: "{other_vault_code}",
+                            oninput: move |e| other_vault_code.set(e.value()),
+                        }
+                        button {
+                            style: "background:#4facfe;border:none;border-radius:12px;padding:12px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: create_other_vault,
+                            "إنشاء الخزنة وفتحها"
+                        }
+                        if !other_vault_message().is_empty() {
+                            div { style: "text-align:center;font-size:14px;", "{other_vault_message}" }
+                        }
+                    }
+                }
+
+                // نموذج تغيير كلمة المرور
+                if show_change_password() {
+                    div {
+                        style: "max-width:800px;margin:0 auto 20px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;display:flex;flex-direction:column;gap:10px;",
+                        input {
+                            style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;",
+                            r#type: "password",
+                            placeholder: "كلمة المرور الحالية",
+                            value: "{old_password}",
+                            oninput: move |e| old_password.set(e.value()),
+                        }
+                        input {
+                            style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;",
+                            r#type: "password",
+                            placeholder: "كلمة المرور الجديدة",
+                            value: "{new_password}",
+                            oninput: move |e| new_password.set(e.value()),
+                        }
+                        input {
+                            style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;",
+                            r#type: "password",
+                            placeholder: "تأكيد كلمة المرور الجديدة",
+                            value: "{new_password_confirm}",
+                            oninput: move |e| new_password_confirm.set(e.value()),
+                        }
+                        button {
+                            style: "background:linear-gradient(135deg,#4facfe 0%,#00f2fe 100%);border:none;border-radius:12px;padding:12px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: submit_change_password,
+                            "حفظ كلمة المرور الجديدة"
+                        }
+                    }
+                }
+                if !change_message().is_empty() {
+                    div {
+                        style: "max-width:800px;margin:0 auto 20px;text-align:center;font-size:14px;",
+                        "{change_message()}"
+                    }
+                }
+
+                // عرض إنشاء عبارة الاسترداد مرة واحدة بعد إنشاء الخزنة
+                if offer_recovery() {
+                    div {
+                        style: "max-width:800px;margin:0 auto 20px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;display:flex;flex-direction:column;gap:10px;",
+                        div { "🛟 أنشئ عبارة استرداد تفتح الخزنة إن نسيت كلمة المرور" }
+                        div { style: "display:flex;gap:10px;",
+                            button {
+                                style: "flex:1;background:linear-gradient(135deg,#4facfe 0%,#00f2fe 100%);border:none;border-radius:12px;padding:12px;color:white;font-weight:700;cursor:pointer;",
+                                onclick: create_recovery_phrase,
+                                "إنشاء عبارة الاسترداد"
+                            }
+                            button {
+                                style: "flex:1;background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px;color:white;cursor:pointer;",
+                                onclick: move |_| offer_recovery.set(false),
+                                "لاحقاً"
+                            }
+                        }
+                    }
+                }
+
+                // العبارة تُعرض هنا فقط، وتُمسح من الذاكرة عند تأكيد حفظها
+                if let Some(recovery_key) = new_recovery_key() {
+                    div {
+                        style: "max-width:800px;margin:0 auto 20px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;display:flex;flex-direction:column;gap:10px;",
+                        div { style: "font-size:13px;opacity:0.8;", "اكتب عبارة الاسترداد واحفظها في مكان آمن - لن تُعرض مرة أخرى" }
+                        div { style: "display:grid;grid-template-columns:repeat(4,1fr);gap:8px;direction:ltr;",
+                            for (number, word) in recovery_key.split_whitespace().enumerate() {
+                                div {
+                                    key: "{number}",
+                                    style: "background:rgba(0,0,0,0.3);border-radius:8px;padding:8px;font-family:monospace;",
+                                    "{number + 1}. {word}"
+                                }
+                            }
+                        }
+                        button {
+                            style: "background:rgba(255,255,255,0.2);border:none;border-radius:12px;padding:12px;color:white;cursor:pointer;",
+                            onclick: move |_| new_recovery_key.set(None),
+                            "✅ حفظتها"
+                        }
+                    }
+                }
 
-    let decrypt_and_open = move |index: usize| {
-        spawn(async move {
-            let files = encrypted_files();
-            if let Some(file) = files.get(index) {
-                if let Ok(decrypted_data) = decrypt_file(&file.encrypted_path) {
-                    let temp_dir = std::env::temp_dir();
-                    let temp_path = temp_dir.join(&file.name);
-                    if fs::write(&temp_path, decrypted_data).is_ok() {
-                        let _ = open::that(&temp_path);
+                // رمز التحقق الزمني: تفعيل بمسح رمز QR في تطبيق مصادقة، أو إيقافه
+                if show_totp() {
+                    div {
+                        style: "max-width:800px;margin:0 auto 20px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;display:flex;flex-direction:column;gap:10px;align-items:center;",
+                        if let Some(totp) = totp_enrolling() {
+                            div { style: "font-size:14px;", "امسح الرمز بتطبيق المصادقة ثم أدخل الرمز الذي يظهر فيه" }
+                            div {
+                                style: "background:white;border-radius:12px;padding:10px;",
+                                dangerous_inner_html: totp.qr_svg().unwrap_or_default(),
+                            }
+                            div { style: "font-family:monospace;font-size:12px;word-break:break-all;user-select:all;direction:ltr;", "{totp.uri()}" }
+                            input {
+                                style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;text-align:center;letter-spacing:4px;",
+                                placeholder: "000000",
+                                maxlength: 6,
+                                value: "{totp_code}",
+                                oninput: move |e| totp_code.set(e.value()),
+                            }
+                            button {
+                                style: "background:linear-gradient(135deg,#4facfe 0%,#00f2fe 100%);border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                                onclick: confirm_totp,
+                                "تأكيد التفعيل"
+                            }
+                        } else if totp_required {
+                            button {
+                                style: "background:#f5576c;border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                                onclick: disable_totp,
+                                "إيقاف رمز التحقق"
+                            }
+                        } else {
+                            button {
+                                style: "background:linear-gradient(135deg,#4facfe 0%,#00f2fe 100%);border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
+                                onclick: move |_| totp_enrolling.set(Some(TotpConfig::generate())),
+                                "تفعيل رمز التحقق"
+                            }
+                        }
+                        if !totp_message().is_empty() {
+                            div { style: "font-size:14px;", "{totp_message}" }
+                        }
                     }
                 }
-            }
-        });
-    };
-
-    let mut delete_file = move |index: usize| {
-        let mut files = encrypted_files();
-        if let Some(file) = files.get(index) {
-            let _ = fs::remove_file(&file.encrypted_path);
-            files.remove(index);
-            save_encrypted_files(&files);
-            encrypted_files.set(files);
-            selected_file_index.set(None);
-        }
-    };
-
-    // دالة فتح مجلد الرفع
-    let open_upload_folder = move |_| {
-        let vault_dir = get_vault_dir();
-        let upload_dir = vault_dir.join("upload");
-        let _ = fs::create_dir_all(&upload_dir);
-        let _ = open::that(&upload_dir);
-    };
 
-    if show_vault() {
-        rsx! {
-            div { 
-                style: "min-height:100vh;background:linear-gradient(135deg,#667eea 0%,#764ba2 100%);color:white;padding:20px;font-family:system-ui,sans-serif;",
-                
-                div { 
-                    style: "max-width:800px;margin:0 auto;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;margin-bottom:20px;display:flex;justify-content:space-between;align-items:center;",
-                    h2 { "🔐 الخزنة السرية" }
-                    button {
-                        style: "background:#f5576c;border:none;border-radius:12px;padding:12px 24px;color:white;font-weight:700;cursor:pointer;",
-                        onclick: move |_| show_vault.set(false),
-                        "إغلاق"
+                // فتحات المفاتيح: كل واحدة تفتح الخزنة وحدها، وحذفها لا يمس الملفات
+                if show_key_slots() {
+                    div {
+                        style: "max-width:800px;margin:0 auto 20px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);padding:20px;border-radius:20px;display:flex;flex-direction:column;gap:10px;",
+                        for (index, slot) in key_slots.iter().enumerate() {
+                            div {
+                                key: "{index}",
+                                style: "display:flex;align-items:center;gap:10px;background:rgba(255,255,255,0.1);border-radius:12px;padding:10px;",
+                                span { style: "flex:1;",
+                                    "{slot.kind.label()} {slot.label}"
+                                    if slot.keyfile.is_some() { " + 📄 ملف مفتاح" }
+                                }
+                                if key_slots.len() > 1 {
+                                    button {
+                                        style: "background:#f5576c;border:none;border-radius:10px;padding:8px 16px;color:white;cursor:pointer;",
+                                        onclick: move |_| remove_slot(index),
+                                        "حذف 🗑️"
+                                    }
+                                }
+                            }
+                        }
+                        div { style: "display:flex;gap:8px;",
+                            for kind in [SlotKind::Password, SlotKind::Recovery, SlotKind::Keyfile] {
+                                button {
+                                    key: "{kind.label()}",
+                                    style: if new_slot_kind() == kind { "flex:1;background:rgba(255,255,255,0.35);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" } else { "flex:1;background:rgba(255,255,255,0.1);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" },
+                                    onclick: move |_| new_slot_kind.set(kind),
+                                    "{kind.label()}"
+                                }
+                            }
+                        }
+                        input {
+                            style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;",
+                            placeholder: "اسم المفتاح (اختياري)",
+                            value: "{new_slot_label}",
+                            oninput: move |e| new_slot_label.set(e.value()),
+                        }
+                        if new_slot_kind() == SlotKind::Password {
+                            input {
+                                style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;",
+                                r#type: "password",
+                                placeholder: "كلمة المرور",
+                                value: "{new_slot_secret}",
+                                oninput: move |e| new_slot_secret.set(e.value()),
+                            }
+                            input {
+                                style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;",
+                                r#type: "password",
+                                placeholder: "تأكيد كلمة المرور",
+                                value: "{new_slot_confirm}",
+                                oninput: move |e| new_slot_confirm.set(e.value()),
+                            }
+                            // عامل ثانٍ: محتوى الملف يدخل الاشتقاق مع كلمة المرور، فلا تكفي وحدها
+                            input {
+                                style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;",
+                                placeholder: "مسار ملف مفتاح يُشترط معها (اختياري)",
+                                value: "{new_slot_keyfile}",
+                                oninput: move |e| new_slot_keyfile.set(e.value()),
+                            }
+                        }
+                        if new_slot_kind() == SlotKind::Keyfile {
+                            input {
+                                style: "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;color:white;",
+                                placeholder: "مسار ملف المفتاح",
+                                value: "{new_slot_secret}",
+                                oninput: move |e| new_slot_secret.set(e.value()),
+                            }
+                        }
+                        button {
+                            style: "background:linear-gradient(135deg,#4facfe 0%,#00f2fe 100%);border:none;border-radius:12px;padding:12px;color:white;font-weight:700;cursor:pointer;",
+                            onclick: add_slot,
+                            "➕ إضافة المفتاح"
+                        }
+                        if !slot_message().is_empty() {
+                            div { style: "text-align:center;font-size:14px;", "{slot_message}" }
+                        }
                     }
                 }
                 
@@ -225,6 +1395,84 @@ fn app() -> Element {
                         }
                     }
 
+                    // حشو الأحجام
+                    div { style: "display:flex;gap:8px;align-items:center;margin-bottom:20px;font-size:14px;",
+                        span { "📏 حشو الأحجام:" }
+                        for policy in PaddingPolicy::ALL {
+                            button {
+                                key: "{policy.label()}",
+                                style: if padding_policy() == policy { "flex:1;background:rgba(255,255,255,0.35);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" } else { "flex:1;background:rgba(255,255,255,0.1);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" },
+                                onclick: move |_| set_padding(policy),
+                                "{policy.label()}"
+                            }
+                        }
+                    }
+
+                    // الحذف الآمن للأصول بعد تشفيرها وللملفات المشفرة المحذوفة
+                    div { style: "display:flex;gap:8px;align-items:center;margin-bottom:20px;font-size:14px;",
+                        span { "🧹 الحذف الآمن:" }
+                        for (passes, label) in [(0, "حذف عادي"), (1, "كتابة مرة"), (3, "3 مرات"), (7, "7 مرات")] {
+                            button {
+                                key: "{passes}",
+                                style: if shred_passes() == passes { "flex:1;background:rgba(255,255,255,0.35);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" } else { "flex:1;background:rgba(255,255,255,0.1);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" },
+                                onclick: move |_| set_shred_passes(passes),
+                                "{label}"
+                            }
+                        }
+                    }
+
+                    // القفل التلقائي
+                    div { style: "display:flex;gap:8px;align-items:center;margin-bottom:20px;font-size:14px;",
+                        span { "⏲️ القفل عند الخمول:" }
+                        for (minutes, label) in [(0, "معطّل"), (1, "دقيقة"), (5, "5 دقائق"), (15, "15 دقيقة")] {
+                            button {
+                                key: "{minutes}",
+                                style: if auto_lock_minutes() == minutes { "flex:1;background:rgba(255,255,255,0.35);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" } else { "flex:1;background:rgba(255,255,255,0.1);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" },
+                                onclick: move |_| set_auto_lock(minutes),
+                                "{label}"
+                            }
+                        }
+                    }
+                    div { style: "display:flex;gap:8px;align-items:center;margin-bottom:20px;font-size:14px;",
+                        span { "🪟 القفل عند مغادرة النافذة:" }
+                        button {
+                            style: if lock_on_blur() { "flex:1;background:rgba(255,255,255,0.35);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" } else { "flex:1;background:rgba(255,255,255,0.1);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" },
+                            onclick: toggle_lock_on_blur,
+                            if lock_on_blur() { "مفعّل (يشمل فتح ملف أو مجلد الرفع)" } else { "معطّل" }
+                        }
+                    }
+
+                    // الإتلاف بعد محاولات فتح فاشلة متتالية
+                    div { style: "display:flex;gap:8px;align-items:center;margin-bottom:20px;font-size:14px;",
+                        span { "🚫 الإتلاف بعد محاولات فاشلة:" }
+                        for (limit, label) in [(None, "أبداً"), (Some(5), "5"), (Some(10), "10"), (Some(20), "20")] {
+                            button {
+                                key: "{label}",
+                                style: if wipe_after() == limit { "flex:1;background:rgba(255,255,255,0.35);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" } else { "flex:1;background:rgba(255,255,255,0.1);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" },
+                                onclick: move |_| set_wipe_after(limit),
+                                "{label}"
+                            }
+                        }
+                    }
+
+                    // الضغط قبل التشفير
+                    div { style: "display:flex;gap:8px;align-items:center;margin-bottom:20px;font-size:14px;",
+                        span { "🗜️ ضغط الملفات:" }
+                        button {
+                            style: if compression() { "flex:1;background:rgba(255,255,255,0.35);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" } else { "flex:1;background:rgba(255,255,255,0.1);border:none;border-radius:10px;padding:8px;color:white;cursor:pointer;" },
+                            onclick: toggle_compression,
+                            if compression() { "مفعّل (الصور والفيديو والأرشيفات تُتخطى)" } else { "معطّل" }
+                        }
+                    }
+
+                    // تقدم ترحيل الملفات القديمة
+                    if !migration_message().is_empty() {
+                        div {
+                            style: "background:rgba(255,255,255,0.1);border-radius:12px;padding:12px;margin-bottom:20px;text-align:center;font-size:14px;white-space:pre-wrap;",
+                            "{migration_message()}"
+                        }
+                    }
+
                     // رسالة الرفع
                     if !upload_message().is_empty() {
                         div {
@@ -233,6 +1481,75 @@ fn app() -> Element {
                         }
                     }
 
+                    // ملف مرفوع محتواه موجود في الخزنة
+                    if let Some(duplicate) = pending_duplicates().first().cloned() {
+                        div {
+                            style: "background:rgba(255,255,255,0.1);border-radius:16px;padding:16px;margin-bottom:20px;font-size:14px;display:flex;flex-direction:column;gap:8px;",
+                            div { style: "font-weight:700;", "♊ ملف مكرر" }
+                            div { "محتوى \"{duplicate.name}\" مطابق للملف الموجود \"{duplicate.existing}\"" }
+                            if pending_duplicates().len() > 1 {
+                                div { style: "opacity:0.8;", "ملفات مكررة أخرى بانتظار القرار: {pending_duplicates().len() - 1}" }
+                            }
+                            div { style: "display:flex;gap:10px;margin-top:8px;",
+                                button {
+                                    style: "flex:1;background:rgba(255,255,255,0.2);border:none;border-radius:10px;padding:10px;color:white;cursor:pointer;",
+                                    onclick: move |_| resolve_duplicate(DuplicateAction::Skip),
+                                    "⏭️ تخطي"
+                                }
+                                button {
+                                    style: "flex:1;background:#4facfe;border:none;border-radius:10px;padding:10px;color:white;font-weight:600;cursor:pointer;",
+                                    onclick: move |_| resolve_duplicate(DuplicateAction::Link),
+                                    "🔗 ربط بالنسخة الموجودة"
+                                }
+                                button {
+                                    style: "flex:1;background:rgba(255,255,255,0.2);border:none;border-radius:10px;padding:10px;color:white;cursor:pointer;",
+                                    onclick: move |_| resolve_duplicate(DuplicateAction::Keep),
+                                    "📄 الاحتفاظ بنسخة"
+                                }
+                            }
+                        }
+                    }
+
+                    // تقرير فحص السلامة
+                    if let Some(report) = scrub_report() {
+                        div {
+                            style: "background:rgba(255,255,255,0.1);border-radius:16px;padding:16px;margin-bottom:20px;font-size:14px;display:flex;flex-direction:column;gap:8px;",
+                            div { style: "font-weight:700;", "🩺 تقرير الفحص" }
+                            div { "✅ ملفات سليمة: {report.checked - report.damaged.len()}" }
+                            div { "❌ ملفات تالفة: {report.damaged.len()}" }
+                            for (path, name, reason) in report.damaged.iter() {
+                                div { key: "{path}", style: "opacity:0.8;padding-right:16px;", "• {name}: {reason}" }
+                            }
+                            div { "❓ إدخالات ملفها مفقود: {report.missing.len()}" }
+                            for (path, name) in report.missing.iter() {
+                                div { key: "{path}", style: "opacity:0.8;padding-right:16px;", "• {name}" }
+                            }
+                            div { "📦 ملفات يتيمة بلا إدخال: {report.orphans.len()}" }
+                            for path in report.orphans.iter() {
+                                div { key: "{path.display()}", style: "opacity:0.8;padding-right:16px;direction:ltr;", "• {path.display()}" }
+                            }
+                            div { style: "display:flex;gap:10px;margin-top:8px;",
+                                if !report.is_healthy() {
+                                    button {
+                                        style: "flex:1;background:#4facfe;border:none;border-radius:10px;padding:10px;color:white;font-weight:600;cursor:pointer;",
+                                        onclick: move |_| resolve_scrub(false),
+                                        "🧳 نقل إلى الحجر"
+                                    }
+                                    button {
+                                        style: "flex:1;background:#f5576c;border:none;border-radius:10px;padding:10px;color:white;font-weight:600;cursor:pointer;",
+                                        onclick: move |_| resolve_scrub(true),
+                                        "🗑️ حذف نهائي"
+                                    }
+                                }
+                                button {
+                                    style: "flex:1;background:rgba(255,255,255,0.2);border:none;border-radius:10px;padding:10px;color:white;cursor:pointer;",
+                                    onclick: move |_| scrub_report.set(None),
+                                    "إغلاق التقرير"
+                                }
+                            }
+                        }
+                    }
+
                     // قائمة الملفات
                     div { 
                         style: "background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);border-radius:20px;padding:20px;max-height:600px;overflow-y:auto;",
@@ -268,6 +1585,9 @@ fn app() -> Element {
                                             div { style: "opacity:0.7;font-size:13px;", 
                                                 "{format_size(file.size)} • {file.created_at}"
                                             }
+                                            if let Some(error) = &file.migration_error {
+                                                div { style: "color:#ffd166;font-size:13px;margin-top:4px;", "⚠️ لم يُرحَّل إلى المفتاح الجديد: {error}" }
+                                            }
                                         }
                                     }
 
@@ -301,6 +1621,17 @@ fn app() -> Element {
         }
     } else {
         render_calculator(input, result, on_key, clear_all, insert, insert_op, calculate, toggle_sign, backspace)
+    };
+
+    // أي نشاط في النافذة يؤجل القفل التلقائي
+    rsx! {
+        div {
+            onmousemove: move |_| last_activity.set(SystemTime::now()),
+            onkeydown: move |_| last_activity.set(SystemTime::now()),
+            onclick: move |_| last_activity.set(SystemTime::now()),
+            onwheel: move |_| last_activity.set(SystemTime::now()),
+            {view}
+        }
     }
 }
 
@@ -369,88 +1700,550 @@ fn render_calculator(
     }
 }
 
-fn encrypt_file(file_name: &str, data: &[u8]) -> Result<EncryptedFile, Box<dyn std::error::Error>> {
-    let mut hasher = Sha256::new();
-    hasher.update(b"49_secret_calculator_key_2024_ultra_secure");
-    let key = hasher.finalize();
+// رمز التحقق قبل شاشة كلمة المرور - بنفس مظهرها
+fn render_totp_prompt(
+    mut totp_input: Signal<String>,
+    auth_error: Signal<String>,
+    submit_totp: Callback<()>,
+    close_vault: Callback<()>,
+) -> Element {
+    let root_style = "min-height:100vh;display:flex;align-items:center;justify-content:center;background:linear-gradient(135deg,#667eea 0%,#764ba2 100%);color:white;font-family:system-ui,sans-serif;padding:20px;";
+    let card_style = "width:380px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);border:1px solid rgba(255,255,255,0.2);border-radius:24px;padding:24px;box-shadow:0 20px 60px rgba(0,0,0,0.3);display:flex;flex-direction:column;gap:12px;";
+    let field_style = "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:14px;font-size:24px;color:white;text-align:center;letter-spacing:6px;";
+    let btn_submit = "background:linear-gradient(135deg,#4facfe 0%,#00f2fe 100%);border:none;border-radius:12px;padding:14px;font-size:18px;font-weight:700;color:white;cursor:pointer;";
+    let btn_cancel = "background:rgba(255,255,255,0.1);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;font-size:16px;color:white;cursor:pointer;";
 
-    let cipher = Aes256Gcm::new(&key.into());
-    
-    let mut nonce_bytes = [0u8; 12];
-    OsRng.fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
+    rsx! {
+        div { style: "{root_style}",
+            div { style: "{card_style}",
+                h2 { "📱 رمز التحقق" }
+                input {
+                    style: "{field_style}",
+                    inputmode: "numeric",
+                    maxlength: 6,
+                    placeholder: "000000",
+                    value: "{totp_input}",
+                    oninput: move |e| totp_input.set(e.value()),
+                    onkeydown: move |e| if e.key() == Key::Enter { submit_totp(()) },
+                }
+                if !auth_error().is_empty() {
+                    div { style: "color:#ffd1d8;font-size:14px;", "{auth_error}" }
+                }
+                button { style: "{btn_submit}", onclick: move |_| submit_totp(()), "متابعة" }
+                button { style: "{btn_cancel}", onclick: move |_| close_vault(()), "رجوع" }
+            }
+        }
+    }
+}
+
+// التشغيل الأول: اختيار رمز الفتح قبل ظهور الحاسبة - بنفس مظهر شاشة الفتح
+fn render_code_setup(
+    mut code: Signal<String>,
+    mut confirm: Signal<String>,
+    message: Signal<String>,
+    submit: Callback<()>,
+) -> Element {
+    let root_style = "min-height:100vh;display:flex;align-items:center;justify-content:center;background:linear-gradient(135deg,#667eea 0%,#764ba2 100%);color:white;font-family:system-ui,sans-serif;padding:20px;";
+    let card_style = "width:380px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);border:1px solid rgba(255,255,255,0.2);border-radius:24px;padding:24px;box-shadow:0 20px 60px rgba(0,0,0,0.3);display:flex;flex-direction:column;gap:12px;";
+    let field_style = "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:14px;font-size:20px;color:white;text-align:center;direction:ltr;";
+    let btn_submit = "background:linear-gradient(135deg,#4facfe 0%,#00f2fe 100%);border:none;border-radius:12px;padding:14px;font-size:18px;font-weight:700;color:white;cursor:pointer;";
+
+    rsx! {
+        div { style: "{root_style}",
+            div { style: "{card_style}",
+                h2 { "🔢 رمز الفتح" }
+                div { style: "font-size:14px;opacity:0.9;",
+                    "اكتبه في الحاسبة ثم اضغط = لفتح الخزنة. رقم مثل 4011 يفتح مع أي عملية نتيجتها 4011، "
+                    "وعملية كاملة مثل 1337*3 لا تفتح إلا بكتابتها كما هي. لا يُحفظ الرمز نفسه ولا يمكن استرجاعه."
+                }
+                input {
+                    style: "{field_style}",
+                    r#type: "password",
+                    placeholder: "الرمز",
+                    value: "{code}",
+                    oninput: move |e| code.set(e.value()),
+                }
+                input {
+                    style: "{field_style}",
+                    r#type: "password",
+                    placeholder: "تأكيد الرمز",
+                    value: "{confirm}",
+                    oninput: move |e| confirm.set(e.value()),
+                    onkeydown: move |e| if e.key() == Key::Enter { submit(()) },
+                }
+                if !message().is_empty() {
+                    div { style: "color:#ffd1d8;font-size:14px;", "{message}" }
+                }
+                button { style: "{btn_submit}", onclick: move |_| submit(()), "حفظ والمتابعة" }
+            }
+        }
+    }
+}
+
+// ملف في مجلد الرفع محتواه موجود في الخزنة - يبقى في مكانه حتى يقرر المستخدم
+#[derive(Clone, PartialEq)]
+struct PendingDuplicate {
+    source: PathBuf,
+    name: String,
+    existing: String, // اسم الإدخال المطابق للعرض
+    content_mac: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DuplicateAction {
+    Skip,
+    Link,
+    Keep,
+}
+
+// حقول شاشة الفتح - مجمعة لأنها تتنقل معاً بين الإنشاء والفتح والاسترداد
+#[derive(Clone, Copy)]
+struct AuthForm {
+    password: Signal<String>,
+    confirm: Signal<String>,
+    keyfile: Signal<String>,
+    recovery_phrase: Signal<String>,
+    recovering: Signal<bool>,
+    error: Signal<String>,
+}
+
+fn render_vault_auth(
+    form: AuthForm,
+    mut vault_cipher: Signal<CipherKind>,
+    submit_password: Callback<()>,
+    submit_recovery: Callback<()>,
+    close_vault: Callback<()>,
+) -> Element {
+    let AuthForm { mut password, mut confirm, mut keyfile, mut recovery_phrase, mut recovering, mut error } = form;
+    let root_style = "min-height:100vh;display:flex;align-items:center;justify-content:center;background:linear-gradient(135deg,#667eea 0%,#764ba2 100%);color:white;font-family:system-ui,sans-serif;padding:20px;";
+    let card_style = "width:380px;background:rgba(255,255,255,0.1);backdrop-filter:blur(20px);border:1px solid rgba(255,255,255,0.2);border-radius:24px;padding:24px;box-shadow:0 20px 60px rgba(0,0,0,0.3);display:flex;flex-direction:column;gap:12px;";
+    let field_style = "background:rgba(0,0,0,0.3);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:14px;font-size:16px;color:white;";
+    let btn_submit = "background:linear-gradient(135deg,#4facfe 0%,#00f2fe 100%);border:none;border-radius:12px;padding:14px;font-size:18px;font-weight:700;color:white;cursor:pointer;";
+    let btn_cancel = "background:rgba(255,255,255,0.1);border:1px solid rgba(255,255,255,0.2);border-radius:12px;padding:12px;font-size:16px;color:white;cursor:pointer;";
+    let btn_link = "background:none;border:none;font-size:14px;color:white;opacity:0.8;cursor:pointer;text-decoration:underline;";
+    let header = VaultHeader::load(&get_vault_dir());
+    let creating = header.is_none();
+    let has_recovery = header.is_some_and(|h| h.slots.iter().any(|slot| slot.kind == SlotKind::Recovery));
 
-    let encrypted_data = cipher.encrypt(nonce, data)
-        .map_err(|e| format!("خطأ في التشفير: {:?}", e))?;
+    // الاسترداد: العبارة ثم كلمة مرور جديدة تُضاف كفتحة بجانب الموجودة
+    if recovering() {
+        return rsx! {
+            div { style: "{root_style}",
+                div { style: "{card_style}",
+                    h2 { "🛟 استرداد الخزنة" }
+                    textarea {
+                        style: "{field_style}min-height:110px;direction:ltr;",
+                        placeholder: "عبارة الاسترداد (24 كلمة)",
+                        value: "{recovery_phrase}",
+                        oninput: move |e| recovery_phrase.set(e.value()),
+                    }
+                    input {
+                        style: "{field_style}",
+                        r#type: "password",
+                        placeholder: "كلمة المرور الجديدة",
+                        value: "{password}",
+                        oninput: move |e| password.set(e.value()),
+                    }
+                    input {
+                        style: "{field_style}",
+                        r#type: "password",
+                        placeholder: "تأكيد كلمة المرور الجديدة",
+                        value: "{confirm}",
+                        oninput: move |e| confirm.set(e.value()),
+                        onkeydown: move |e| if e.key() == Key::Enter { submit_recovery(()) },
+                    }
+                    if !error().is_empty() {
+                        div { style: "color:#ffd1d8;font-size:14px;", "{error}" }
+                    }
+                    button { style: "{btn_submit}", onclick: move |_| submit_recovery(()), "استرداد وتعيين كلمة المرور" }
+                    button {
+                        style: "{btn_cancel}",
+                        onclick: move |_| {
+                            recovering.set(false);
+                            recovery_phrase.set(String::new());
+                            error.set(String::new());
+                        },
+                        "رجوع"
+                    }
+                }
+            }
+        };
+    }
 
-    let mut final_data = nonce_bytes.to_vec();
-    final_data.extend_from_slice(&encrypted_data);
+    rsx! {
+        div { style: "{root_style}",
+            div { style: "{card_style}",
+                h2 { if creating { "🔐 إنشاء كلمة مرور للخزنة" } else { "🔐 أدخل كلمة مرور الخزنة" } }
+                input {
+                    style: "{field_style}",
+                    r#type: "password",
+                    placeholder: if creating { "كلمة المرور" } else { "كلمة المرور أو عبارة الاسترداد" },
+                    value: "{password}",
+                    oninput: move |e| password.set(e.value()),
+                    onkeydown: move |e| if e.key() == Key::Enter { submit_password(()) },
+                }
+                if !creating {
+                    input {
+                        style: "{field_style}",
+                        placeholder: "أو مسار ملف المفتاح",
+                        value: "{keyfile}",
+                        oninput: move |e| keyfile.set(e.value()),
+                        onkeydown: move |e| if e.key() == Key::Enter { submit_password(()) },
+                    }
+                }
+                if creating {
+                    input {
+                        style: "{field_style}",
+                        r#type: "password",
+                        placeholder: "تأكيد كلمة المرور",
+                        value: "{confirm}",
+                        oninput: move |e| confirm.set(e.value()),
+                        onkeydown: move |e| if e.key() == Key::Enter { submit_password(()) },
+                    }
+                    // خوارزمية التشفير - XChaCha20 أسرع على المعالجات التي لا تدعم AES-NI
+                    div { style: "display:flex;gap:8px;",
+                        for kind in CipherKind::ALL {
+                            button {
+                                key: "{kind.id()}",
+                                style: if vault_cipher() == kind { "{btn_cancel}flex:1;background:rgba(255,255,255,0.35);" } else { "{btn_cancel}flex:1;" },
+                                onclick: move |_| vault_cipher.set(kind),
+                                "{kind.label()}"
+                            }
+                        }
+                    }
+                }
+                if !error().is_empty() {
+                    div { style: "color:#ffd1d8;font-size:14px;", "{error}" }
+                }
+                button { style: "{btn_submit}", onclick: move |_| submit_password(()),
+                    if creating { "إنشاء الخزنة" } else { "فتح" }
+                }
+                if has_recovery {
+                    button {
+                        style: "{btn_link}",
+                        onclick: move |_| {
+                            recovering.set(true);
+                            password.set(String::new());
+                            error.set(String::new());
+                        },
+                        "نسيت كلمة المرور؟"
+                    }
+                }
+                button { style: "{btn_cancel}", onclick: move |_| close_vault(()), "رجوع" }
+            }
+        }
+    }
+}
 
+fn encrypt_file(file_name: &str, reader: impl Read, size: u64, master_key: &[u8; 32]) -> Result<EncryptedFile, Box<dyn std::error::Error>> {
+    // مفتاح بيانات جديد لكل ملف - تسريبه لا يكشف إلا هذا الملف
+    let data_key = generate_key();
+    let file_id = generate_file_id();
     let vault_dir = get_vault_dir();
+    let (cipher, padding, compression) = VaultHeader::load(&vault_dir)
+        .map(|h| (h.cipher, h.padding, h.compression))
+        .unwrap_or_default();
+
+    // مختصر النص الأصلي يُحسب أثناء التشفير ويُحفظ في الفهرس المشفر لفحص السلامة لاحقاً.
+    // بداية الملف تُقرأ أولاً للتعرف على الصيغ المضغوطة أصلاً، ثم تُعاد أمام بقيته
+    let mut hashing = HashingReader::new(reader);
+    let mut sample = Zeroizing::new(Vec::with_capacity(SNIFF_LEN));
+    (&mut hashing).take(SNIFF_LEN as u64).read_to_end(&mut sample)?;
+    let compressed = compression && is_compressible(&sample);
+
+    let mut flags = if padding == PaddingPolicy::None { 0 } else { FLAG_PADDED };
+    if compressed {
+        flags |= FLAG_COMPRESSED;
+    }
+
+    let extension = file_name.split('.').last().unwrap_or("").to_string();
+    let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+    let header = ContainerHeader::new(cipher, flags, &data_key, &FileMetadata {
+        file_id: file_id.clone(),
+        name: file_name.to_string(),
+        original_extension: extension.clone(),
+        created_at: created_at.clone(),
+    })?;
+
     fs::create_dir_all(&vault_dir)?;
     
     let encrypted_filename = format!("{}.secure", generate_random_id());
     let encrypted_path = vault_dir.join(&encrypted_filename);
-    fs::write(&encrypted_path, &final_data)?;
-
-    let extension = file_name.split('.').last().unwrap_or("").to_string();
-    let now = chrono::Local::now();
+    let output = BufWriter::new(fs::File::create(&encrypted_path)?);
+    let identity = file_identity(&file_id, file_name, &extension);
+    let plaintext = (&sample[..]).chain(&mut hashing);
+    let written = if compressed {
+        compress_reader(plaintext)
+            .map_err(Into::into)
+            .and_then(|compressed| header.write_body(&data_key, &identity, padding.pad_compressed_reader(compressed), output))
+    } else if flags & FLAG_PADDED != 0 {
+        header.write_body(&data_key, &identity, padding.pad_reader(plaintext, size), output)
+    } else {
+        header.write_body(&data_key, &identity, plaintext, output)
+    };
+    // الحجم المسجل هو حجم النص الأصلي، لا حجم الحمولة المضغوطة أو المحشوة
+    let size = match written {
+        Ok(_) if compressed => hashing.bytes_read(),
+        Ok(written) if !compressed && flags & FLAG_PADDED == 0 => written,
+        Ok(written) if !compressed && written == padding.payload_len(size) => size,
+        result => {
+            let _ = fs::remove_file(&encrypted_path);
+            result?;
+            return Err("تغير حجم الملف أثناء التشفير".into());
+        }
+    };
+    let sha256 = hashing.finish();
     
     Ok(EncryptedFile {
+        file_id,
         name: file_name.to_string(),
         original_extension: extension,
         encrypted_path: encrypted_path.to_string_lossy().to_string(),
-        size: data.len() as u64,
-        created_at: now.format("%Y-%m-%d %H:%M").to_string(),
+        size,
+        created_at,
+        scheme: KeyScheme::Envelope,
+        wrapped_key: wrap_key(master_key, &data_key)?,
+        content_mac: content_mac(master_key, &sha256),
+        sha256,
+        blob_name: None,
+        migration_error: None,
     })
 }
 
-fn decrypt_file(encrypted_path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let data = fs::read(encrypted_path)?;
+fn decrypt_file(file: &EncryptedFile, master_key: &[u8; 32], mut writer: impl Write) -> Result<(), Box<dyn std::error::Error>> {
+    let mut source = BufReader::new(fs::File::open(&file.encrypted_path)?);
+
+    if let Layout::Container(header) = ContainerHeader::detect(&mut source)? {
+        let data_key = unwrap_key(master_key, &file.wrapped_key)?;
+
+        // الحاوية يجب أن تنتمي لهذا الإدخال بالذات - ملف مبدّل أو مسار موجّه لملف آخر يُرفض
+        // الإدخال المرتبط بحاوية إدخال آخر يُطابق بالاسم المسجل داخلها
+        let blob_name = file.blob_name.as_deref().unwrap_or(&file.name);
+        let metadata = header.open_metadata(&data_key)
+            .map_err(|_| "الملف المشفر لا ينتمي لهذا الإدخال")?;
+        if metadata.file_id != file.file_id
+            || metadata.name != blob_name
+            || metadata.original_extension != file.original_extension
+        {
+            return Err("الملف المشفر لا ينتمي لهذا الإدخال".into());
+        }
+
+        let identity = file_identity(&file.file_id, blob_name, &file.original_extension);
+        if header.flags & FLAG_COMPRESSED != 0 {
+            // فك الضغط بشفافية - وحشو الحمولة المضغوطة إطارات يتخطاها فك الضغط نفسه
+            let mut decompressed = DecompressWriter::new(writer)?;
+            header.read_body(&data_key, &identity, source, &mut decompressed)?;
+            decompressed.finish()?;
+        } else if header.flags & FLAG_PADDED != 0 {
+            // الحشو يُزال بشفافية - لا يُكتب إلا الطول الحقيقي المخزن داخل الحمولة الموثّقة
+            let mut unpadded = UnpadWriter::new(writer);
+            header.read_body(&data_key, &identity, source, &mut unpadded)?;
+            unpadded.finish()?;
+        } else {
+            header.read_body(&data_key, &identity, source, writer)?;
+        }
+        return Ok(());
+    }
+
+    // إدخال بالصيغة الجديدة لا يقبل ملفاً بالصيغة القديمة - مفتاحها الثابت معروف لأي مهاجم
+    if file.scheme != KeyScheme::Legacy {
+        return Err("الملف المشفر لا ينتمي لهذا الإدخال".into());
+    }
+
+    // الصيغة القديمة بلا ترويسة: nonce || ciphertext بالمفتاح الثابت القديم
+    let mut data = Vec::new();
+    source.read_to_end(&mut data)?;
     
     if data.len() < 12 {
         return Err("ملف غير صالح".into());
     }
     
     let (nonce_bytes, encrypted_data) = data.split_at(12);
-    let nonce = Nonce::from_slice(nonce_bytes);
-
-    let mut hasher = Sha256::new();
-    hasher.update(b"49_secret_calculator_key_2024_ultra_secure");
-    let key = hasher.finalize();
+    let nonce = Nonce::from(<[u8; 12]>::try_from(nonce_bytes)?);
 
-    let cipher = Aes256Gcm::new(&key.into());
-    let decrypted_data = cipher.decrypt(nonce, encrypted_data)
-        .map_err(|e| format!("خطأ في فك التشفير: {:?}", e))?;
+    let cipher = Aes256Gcm::new_from_slice(&legacy_key())?;
+    let decrypted_data = Zeroizing::new(cipher.decrypt(&nonce, encrypted_data)
+        .map_err(|e| format!("خطأ في فك التشفير: {:?}", e))?);
     
-    Ok(decrypted_data)
+    writer.write_all(&decrypted_data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// تشفير ملف من مجلد الرفع وإضافته إلى الفهرس ثم حذف الأصل
+fn import_upload(
+    path: &Path,
+    file_name: &str,
+    key: &[u8; 32],
+    files: &mut Signal<Vec<EncryptedFile>>,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let source = fs::File::open(path)?;
+    let size = source.metadata()?.len();
+    // تشفير الملف قطعةً قطعة دون تحميله كاملاً - بلا BufReader
+    // حتى لا يبقى نص أصلي في مخزن وسيط لا يُمسح
+    let encrypted_info = encrypt_file(file_name, source, size, key)?;
+    let blob = PathBuf::from(&encrypted_info.encrypted_path);
+    let mut all = files();
+    all.push(encrypted_info);
+    // الأصل يبقى إن تعذر حفظ الفهرس - والحاوية الجديدة لا يشير إليها شيء فتُحذف
+    if let Err(e) = save_encrypted_files(&all, key) {
+        let _ = fs::remove_file(&blob);
+        return Err(e);
+    }
+    files.set(all);
+
+    // حذف الملف الأصلي حذفاً آمناً بعد التشفير - تعذر حذفه لا يلغي التشفير لكنه يُبلغ
+    Ok(shred_file(path).unwrap_or_else(|e| Some(format!("تعذر حذف الملف الأصلي: {}", e))))
+}
+
+// إدخال جديد باسم الملف المرفوع يشير إلى حاوية الملف المطابق بدل تشفير نسخة ثانية
+fn link_upload(
+    duplicate: &PendingDuplicate,
+    key: &[u8; 32],
+    files: &mut Signal<Vec<EncryptedFile>>,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut all = files();
+    let existing = all.iter()
+        .find(|f| f.content_mac == duplicate.content_mac)
+        .cloned()
+        .ok_or("الملف المطابق لم يعد في الخزنة")?;
+    let blob_name = existing.blob_name.clone().unwrap_or_else(|| existing.name.clone());
+    all.push(EncryptedFile {
+        name: duplicate.name.clone(),
+        created_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        blob_name: (blob_name != duplicate.name).then_some(blob_name),
+        ..existing
+    });
+    // الأصل لا يُحذف قبل أن يُحفظ الإدخال الجديد
+    save_encrypted_files(&all, key)?;
+    files.set(all);
+
+    Ok(shred_file(&duplicate.source).unwrap_or_else(|e| Some(format!("تعذر حذف الملف الأصلي: {}", e))))
+}
+
+// حذف آمن بعدد المرات المضبوط في الخزنة - يعيد تحذيراً إن تعذر ضمان الكتابة فوق المحتوى
+fn shred_file(path: &Path) -> io::Result<Option<String>> {
+    let passes = VaultHeader::load(&get_vault_dir()).map(|h| h.shred_passes).unwrap_or(DEFAULT_SHRED_PASSES);
+    secure_delete(path, passes)
+}
+
+fn with_caveat(message: String, caveat: Option<String>) -> String {
+    match caveat {
+        Some(caveat) => format!("{}\n⚠️ {}", message, caveat),
+        None => message,
+    }
+}
+
+// SHA-256 لملف قبل استيراده، بمخزن قراءة يُمسح بعد الاستخدام
+fn hash_file(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let mut hashing = HashingReader::new(fs::File::open(path)?);
+    let mut buffer = Zeroizing::new(vec![0u8; 64 * 1024]);
+    while hashing.read(&mut buffer)? > 0 {}
+    Ok(hashing.finish())
+}
+
+// ترحيل ملف بالصيغة القديمة: فك بالمفتاح الثابت ثم إعادة تشفيره في حاوية بمفتاح بيانات جديد
+fn migrate_legacy_file(file: &EncryptedFile, master_key: &[u8; 32]) -> Result<EncryptedFile, Box<dyn std::error::Error>> {
+    // السعة محجوزة مسبقاً بالحجم المعروف حتى لا يترك إعادة الحجز نسخاً غير ممسوحة
+    let mut data = Zeroizing::new(Vec::with_capacity(file.size as usize));
+    decrypt_file(file, master_key, &mut *data)?;
+
+    let mut migrated = encrypt_file(&file.name, &data[..], data.len() as u64, master_key)?;
+    migrated.created_at = file.created_at.clone();
+    Ok(migrated)
+}
+
+// العملية كما كُتبت، بصيغة ثابتة: نفس الرموز التي يُقيّمها eval_str بلا مسافات ولا "=" أخيرة
+fn normalize_expression(text: &str) -> String {
+    let expr: String = text.replace('×', "*").replace('÷', "/").split_whitespace().collect();
+    expr.trim_end_matches('=').to_string()
+}
+
+// الرمز كما يُشتق ويُقارن: الرقم وحده يطابق أي عملية نتيجتها هو، والعملية تطابق نفسها فقط
+fn code_from_setting(text: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let expr = normalize_expression(text);
+    if let Ok(v) = expr.parse::<f64>() {
+        return Ok(format!("value:{}", format_result(v)));
+    }
+    match eval_str(&expr) {
+        Ok(_) => Ok(format!("expr:{}", expr)),
+        Err(_) => Err("الرمز يجب أن يكون رقماً أو عملية حسابية".into()),
+    }
+}
+
+fn describe_code(code: &str) -> &str {
+    code.split_once(':').map_or(code, |(_, text)| text)
+}
+
+// محاولة فتح فاشلة (كلمة مرور أو ملف مفتاح أو عبارة استرداد أو رمز تحقق): تُسجل مع انتظار
+// يتضاعف، وعند بلوغ الحد المضبوط تُتلف مفاتيح الخزنة بصمت - الرسالة تبقى رسالة الخطأ المعتادة
+fn failed_unlock(vault_dir: &Path, attempts: &AttemptsKey) {
+    let Ok(failures) = record_failed_unlock(attempts) else { return };
+    let wipe_after = VaultHeader::load(vault_dir).and_then(|h| h.wipe_after);
+    if wipe_after.is_some_and(|limit| failures >= limit) {
+        let _ = destroy_vault(vault_dir);
+    }
+}
+
+fn wait_message(secs: u64) -> String {
+    if secs >= 60 {
+        format!("⏳ محاولات كثيرة - حاول مرة أخرى بعد {} دقيقة", secs.div_ceil(60))
+    } else {
+        format!("⏳ محاولات كثيرة - حاول مرة أخرى بعد {} ثانية", secs)
+    }
+}
+
+// نتيجة العملية كما تظهر على الشاشة - وهي نفسها ما يُقارن برموز الخزنات
+fn format_result(v: f64) -> String {
+    if v.fract().abs() < 1e-12 {
+        format!("{}", v.round() as i64)
+    } else {
+        let s = format!("{:.12}", v);
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+// الخزنة المفتوحة حالياً - تُختار من الحاسبة قبل إظهار شاشة الفتح
+static ACTIVE_VAULT_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+fn select_vault(vault_dir: PathBuf) {
+    if let Ok(mut active) = ACTIVE_VAULT_DIR.write() {
+        *active = Some(vault_dir);
+    }
 }
 
 fn get_vault_dir() -> PathBuf {
+    if let Some(vault_dir) = ACTIVE_VAULT_DIR.read().ok().and_then(|active| active.clone()) {
+        return vault_dir;
+    }
+    primary_vault_dir()
+}
+
+fn primary_vault_dir() -> PathBuf {
     let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
     path.push(".calculator_vault");
     path
 }
 
-fn load_encrypted_files(files: &mut Signal<Vec<EncryptedFile>>) {
+fn load_encrypted_files(files: &mut Signal<Vec<EncryptedFile>>, key: &[u8; 32], message: &mut Signal<String>) {
     let vault_dir = get_vault_dir();
-    let index_path = vault_dir.join("index.json");
-    
-    if let Ok(data) = fs::read_to_string(&index_path) {
-        if let Ok(loaded_files) = serde_json::from_str(&data) {
+    match load_index(&vault_dir, key) {
+        Ok(loaded_files) => {
+            // فهرس موثّق لكنه أقدم من آخر حفظ: يُعرض مع تحذير بدل الثقة به بصمت
+            if let Some(warning) = check_index_freshness(&vault_dir, key).warning() {
+                message.set(warning);
+            }
             files.set(loaded_files);
         }
+        Err(e) => {
+            files.set(Vec::new());
+            message.set(format!("❌ {}", e));
+        }
     }
 }
 
-fn save_encrypted_files(files: &[EncryptedFile]) {
-    let vault_dir = get_vault_dir();
-    let _ = fs::create_dir_all(&vault_dir);
-    let index_path = vault_dir.join("index.json");
-    
-    if let Ok(json) = serde_json::to_string_pretty(files) {
-        let _ = fs::write(&index_path, json);
-    }
+fn save_encrypted_files(files: &[EncryptedFile], key: &[u8; 32]) -> Result<(), Box<dyn std::error::Error>> {
+    save_index(&get_vault_dir(), key, files)
 }
 
 fn generate_random_id() -> String {
@@ -0,0 +1,17 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+// كتابة ذرية: ملف مؤقت بجانب الهدف ثم إعادة تسمية - الانقطاع لا يترك ملفاً نصف مكتوب
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)
+}
@@ -0,0 +1,218 @@
+use aes_gcm::aead::OsRng;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::atomic::write_atomic;
+use super::codes::constant_time_eq;
+use super::secret::SecretKey;
+
+// محاولات الفتح الفاشلة لكل خزنة في ملف واحد بحجم ثابت، يُنشأ ببايتات عشوائية:
+//   ATTEMPT_SLOTS × (nonce 16 | السجل XOR قناع 12 | HMAC مختصر 16)
+// السجل: عدد المحاولات u32 | لا محاولة قبل (ثوانٍ يونكس) u64، مختوماً بمفتاح مشتق من الرمز
+// الذي فتح شاشة الخزنة (انظر codes.rs). لا مفتاح في الملف، فسجل بلا رمزه لا يختلف عن البايتات
+// العشوائية حوله ولا يدل الملف على عدد الخزنات ولا على أيها سُجلت عليه محاولة. سجل جديد يأخذ
+// موضعاً عشوائياً، وقد يقع على سجل خزنة أخرى فيصفّر عدادها - نادر، والسجلات تُمحى مع أول فتح
+// ناجح. حذف الملف يصفّر العداد - الحماية الحقيقية تبقى اشتقاق كلمة المرور
+const ATTEMPTS_FILE: &str = "session";
+const ATTEMPT_SLOTS: usize = 64;
+const PLAIN_LEN: usize = 4 + 8;
+const RECORD_LEN: usize = 16 + PLAIN_LEN + 16;
+const TABLE_LEN: usize = ATTEMPT_SLOTS * RECORD_LEN;
+
+// أول محاولات بلا انتظار (خطأ كتابة عادي)، ثم انتظار يتضاعف حتى ساعة
+const FREE_ATTEMPTS: u32 = 3;
+const BASE_DELAY_SECS: u64 = 5;
+const MAX_DELAY_SECS: u64 = 60 * 60;
+
+// مفتاح سجل محاولات خزنة واحدة - يُعطى مع الخزنة التي طابقها الرمز ويبقى مع شاشتها
+#[derive(Clone)]
+pub struct AttemptsKey(SecretKey);
+
+impl AttemptsKey {
+    pub(super) fn new(key: SecretKey) -> Self {
+        AttemptsKey(key)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Attempts {
+    failures: u32,
+    not_before: u64,
+}
+
+fn table_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("calculator").join(ATTEMPTS_FILE))
+}
+
+// الصيغة الأقدم (بمفتاح في أول الملف) طولها مختلف فتُستبدل بجدول عشوائي
+fn load_table(path: &Path) -> Vec<u8> {
+    fs::read(path).ok().filter(|table| table.len() == TABLE_LEN).unwrap_or_else(|| {
+        let mut table = vec![0u8; TABLE_LEN];
+        OsRng.fill_bytes(&mut table);
+        table
+    })
+}
+
+fn save_table(path: &Path, table: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(path, table)?;
+    Ok(())
+}
+
+fn record_offset(slot: usize) -> usize {
+    slot * RECORD_LEN
+}
+
+fn keyed(key: &[u8], label: &[u8], nonce: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC يقبل أي طول مفتاح");
+    mac.update(label);
+    mac.update(nonce);
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+// السجل إن كان مختوماً بهذا المفتاح - سجل خزنة أخرى أو بايتات عشوائية لا تتحقق
+fn open_record(table: &[u8], offset: usize, key: &AttemptsKey) -> Option<Attempts> {
+    let record = &table[offset..offset + RECORD_LEN];
+    let (nonce, rest) = record.split_at(16);
+    let mask = keyed(&*key.0, b"mask", nonce, &[]);
+    let plain: Vec<u8> = rest[..PLAIN_LEN].iter().zip(mask).map(|(a, b)| a ^ b).collect();
+    if !constant_time_eq(&keyed(&*key.0, b"tag", nonce, &plain)[..16], &rest[PLAIN_LEN..]) {
+        return None;
+    }
+    Some(Attempts {
+        failures: u32::from_be_bytes(plain[..4].try_into().ok()?),
+        not_before: u64::from_be_bytes(plain[4..12].try_into().ok()?),
+    })
+}
+
+fn seal_record(table: &mut [u8], offset: usize, key: &AttemptsKey, attempts: Attempts) {
+    let mut nonce = [0u8; 16];
+    OsRng.fill_bytes(&mut nonce);
+    let mut plain = [0u8; PLAIN_LEN];
+    plain[..4].copy_from_slice(&attempts.failures.to_be_bytes());
+    plain[4..12].copy_from_slice(&attempts.not_before.to_be_bytes());
+
+    let mask = keyed(&*key.0, b"mask", &nonce, &[]);
+    let tag = keyed(&*key.0, b"tag", &nonce, &plain);
+    let record = &mut table[offset..offset + RECORD_LEN];
+    record[..16].copy_from_slice(&nonce);
+    for (out, (a, b)) in record[16..16 + PLAIN_LEN].iter_mut().zip(plain.iter().zip(mask)) {
+        *out = a ^ b;
+    }
+    record[16 + PLAIN_LEN..].copy_from_slice(&tag[..16]);
+}
+
+// موضع سجل هذا المفتاح إن وُجد
+fn find_record(table: &[u8], key: &AttemptsKey) -> Option<(usize, Attempts)> {
+    (0..ATTEMPT_SLOTS).find_map(|slot| open_record(table, record_offset(slot), key).map(|attempts| (slot, attempts)))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+// سجل هذه الخزنة، أو سجل فارغ إن لم تُسجَّل لها محاولة فاشلة
+fn read_attempts(table: &[u8], key: &AttemptsKey) -> Attempts {
+    find_record(table, key).map(|(_, attempts)| attempts).unwrap_or_default()
+}
+
+fn backoff_secs(failures: u32) -> u64 {
+    if failures < FREE_ATTEMPTS {
+        return 0;
+    }
+    let doublings = (failures - FREE_ATTEMPTS).min(20);
+    (BASE_DELAY_SECS << doublings).min(MAX_DELAY_SECS)
+}
+
+// الانتظار المتبقي قبل قبول محاولة أخرى لهذه الخزنة. ساعة أُرجعت للوراء لا تطيل الانتظار عن الحد
+pub fn unlock_delay(key: &AttemptsKey) -> Option<u64> {
+    let table = load_table(&table_path()?);
+    let remaining = read_attempts(&table, key).not_before.saturating_sub(now_secs());
+    (remaining > 0).then_some(remaining.min(MAX_DELAY_SECS))
+}
+
+// تسجيل محاولة فاشلة - يعيد عدد المحاولات المتتالية حتى الآن
+pub fn record_failed_unlock(key: &AttemptsKey) -> Result<u32, Box<dyn std::error::Error>> {
+    let path = table_path().ok_or("مجلد الإعدادات غير متاح")?;
+    let mut table = load_table(&path);
+    let failures = record_in(&mut table, key);
+    save_table(&path, &table)?;
+    Ok(failures)
+}
+
+fn record_in(table: &mut [u8], key: &AttemptsKey) -> u32 {
+    let (slot, mut attempts) = find_record(table, key)
+        .unwrap_or_else(|| ((OsRng.next_u32() as usize) % ATTEMPT_SLOTS, Attempts::default()));
+    attempts.failures = attempts.failures.saturating_add(1);
+    attempts.not_before = now_secs() + backoff_secs(attempts.failures);
+    seal_record(table, record_offset(slot), key, attempts);
+    attempts.failures
+}
+
+// بعد فتح ناجح أو إتلاف: السجل يعود عشوائياً. يعيد عدد المحاولات الفاشلة التي سبقته
+pub fn reset_failed_unlocks(key: &AttemptsKey) -> u32 {
+    let Some(path) = table_path() else { return 0 };
+    let mut table = load_table(&path);
+    let Some((slot, attempts)) = find_record(&table, key) else { return 0 };
+    let offset = record_offset(slot);
+    OsRng.fill_bytes(&mut table[offset..offset + RECORD_LEN]);
+    let _ = save_table(&path, &table);
+    attempts.failures
+}
+
+// يُستدعى مرة عند كل تشغيل: الملف يُعاد حفظه فلا يدل وقت تعديله على محاولة فتح
+pub fn refresh_attempts() -> Result<(), Box<dyn std::error::Error>> {
+    let path = table_path().ok_or("مجلد الإعدادات غير متاح")?;
+    save_table(&path, &load_table(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::keys::generate_key;
+
+    fn random_table() -> Vec<u8> {
+        let mut table = vec![0u8; TABLE_LEN];
+        OsRng.fill_bytes(&mut table);
+        table
+    }
+
+    #[test]
+    fn vaults_keep_separate_counters() {
+        let (first, second) = (AttemptsKey::new(generate_key()), AttemptsKey::new(generate_key()));
+        let mut table = random_table();
+        for expected in 1..=3 {
+            assert_eq!(record_in(&mut table, &first), expected);
+        }
+        // الموضع العشوائي قد يقع على سجل الأولى - يُعاد حتى يُختبر التجاور لا التصادم
+        let table = loop {
+            let mut candidate = table.clone();
+            record_in(&mut candidate, &second);
+            if find_record(&candidate, &first).is_some() {
+                break candidate;
+            }
+        };
+        assert_eq!(read_attempts(&table, &first).failures, 3);
+        assert_eq!(read_attempts(&table, &second).failures, 1);
+    }
+
+    #[test]
+    fn records_need_their_key() {
+        let key = AttemptsKey::new(generate_key());
+        let mut table = random_table();
+        record_in(&mut table, &key);
+        let (slot, attempts) = find_record(&table, &key).unwrap();
+        assert_eq!(attempts.failures, 1);
+        // كل موضع آخر وكل مفتاح آخر لا يتحقق: لا فرق بين السجل والبايتات حوله
+        let other = AttemptsKey::new(generate_key());
+        assert!(find_record(&table, &other).is_none());
+        assert!((0..ATTEMPT_SLOTS).filter(|&s| s != slot).all(|s| open_record(&table, record_offset(s), &key).is_none()));
+    }
+}
@@ -0,0 +1,145 @@
+// generic-array 0.14 يعلن نفسه مهملاً، لكن aead 0.5 ما زال يطلب ArrayLength في قيود الأنواع
+#![allow(deprecated)]
+
+use aes_gcm::aead::consts::U5;
+use aes_gcm::aead::generic_array::{typenum::Unsigned, ArrayLength};
+use aes_gcm::aead::{Aead, AeadCore, AeadInPlace, KeyInit, Nonce, OsRng, Payload};
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::ops::Sub;
+
+use super::stream::{decrypt_stream, encrypt_stream};
+
+// الخوارزمية تُختار لكل خزنة عند إنشائها، ويُسجّل معرّفها في ترويسة كل ملف
+// فتبقى الخزنات المختلطة قابلة للفك بعد أي تغيير في الاختيار
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum CipherKind {
+    #[default]
+    Aes256Gcm,
+    XChaCha20Poly1305,
+}
+
+impl CipherKind {
+    pub const ALL: [CipherKind; 2] = [CipherKind::Aes256Gcm, CipherKind::XChaCha20Poly1305];
+
+    pub fn id(self) -> u8 {
+        match self {
+            CipherKind::Aes256Gcm => 1,
+            CipherKind::XChaCha20Poly1305 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Self, Box<dyn std::error::Error>> {
+        CipherKind::ALL
+            .into_iter()
+            .find(|kind| kind.id() == id)
+            .ok_or_else(|| format!("خوارزمية تشفير غير مدعومة: {}", id).into())
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CipherKind::Aes256Gcm => "AES-256-GCM",
+            CipherKind::XChaCha20Poly1305 => "XChaCha20-Poly1305",
+        }
+    }
+
+    pub fn backend(self) -> Box<dyn FileCipher> {
+        match self {
+            CipherKind::Aes256Gcm => Box::new(AeadBackend::<Aes256Gcm>::default()),
+            CipherKind::XChaCha20Poly1305 => Box::new(AeadBackend::<XChaCha20Poly1305>::default()),
+        }
+    }
+}
+
+// واجهة التشفير التي تستخدمها الحاوية - رسالة واحدة للبيانات الوصفية وتدفق لمحتوى الملف
+pub trait FileCipher {
+    fn seal(&self, key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+
+    fn open(&self, key: &[u8; 32], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+
+    fn encrypt_stream(
+        &self,
+        key: &[u8; 32],
+        chunk_size: usize,
+        aad: &[u8],
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<u64, Box<dyn std::error::Error>>;
+
+    fn decrypt_stream(
+        &self,
+        key: &[u8; 32],
+        chunk_size: usize,
+        aad: &[u8],
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<u64, Box<dyn std::error::Error>>;
+}
+
+struct AeadBackend<A>(PhantomData<A>);
+
+impl<A> Default for AeadBackend<A> {
+    fn default() -> Self {
+        AeadBackend(PhantomData)
+    }
+}
+
+impl<A> FileCipher for AeadBackend<A>
+where
+    A: AeadInPlace + KeyInit,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+{
+    // nonce عشوائي || ciphertext
+    fn seal(&self, key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let cipher = A::new_from_slice(key)?;
+        let mut nonce = Nonce::<A>::default();
+        OsRng.fill_bytes(&mut nonce);
+        let sealed = cipher.encrypt(&nonce, Payload { msg: plaintext, aad })
+            .map_err(|e| format!("خطأ في التشفير: {:?}", e))?;
+
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    fn open(&self, key: &[u8; 32], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let nonce_size = <A as AeadCore>::NonceSize::USIZE;
+        if sealed.len() < nonce_size {
+            return Err("بيانات مشفرة غير صالحة".into());
+        }
+        let (nonce_bytes, ciphertext) = sealed.split_at(nonce_size);
+        let mut nonce = Nonce::<A>::default();
+        nonce.copy_from_slice(nonce_bytes);
+
+        let cipher = A::new_from_slice(key)?;
+        cipher.decrypt(&nonce, Payload { msg: ciphertext, aad })
+            .map_err(|_| "خطأ في فك التشفير".into())
+    }
+
+    fn encrypt_stream(
+        &self,
+        key: &[u8; 32],
+        chunk_size: usize,
+        aad: &[u8],
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        encrypt_stream(A::new_from_slice(key)?, chunk_size, aad, reader, writer)
+    }
+
+    fn decrypt_stream(
+        &self,
+        key: &[u8; 32],
+        chunk_size: usize,
+        aad: &[u8],
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        decrypt_stream(A::new_from_slice(key)?, chunk_size, aad, reader, writer)
+    }
+}
@@ -0,0 +1,252 @@
+use aes_gcm::aead::OsRng;
+use rand::RngCore;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use super::atomic::write_atomic;
+use super::attempts::AttemptsKey;
+use super::decoy::plant_decoys;
+use super::duress::{self, DuressMatch, DURESS_TABLE_LEN, LEGACY_DURESS_LEN};
+use super::header::VaultHeader;
+use super::kdf::{derive_key, KdfParams};
+use super::keys::derive_subkey;
+use super::secret::SecretKey;
+
+// رموز الحاسبة في ملف واحد بحجم ثابت في مجلد الإعدادات، يُنشأ عند الإعداد الأول:
+//   salt 16 | مُحقِّق رمز الفتح 32 | سجلات رموز الإتلاف (انظر duress.rs)
+// الرمز نص بصيغة "value:<النتيجة>" أو "expr:<العملية>" ولا يُحفظ هو ولا مختصر سريع له:
+// كل ما في الملف مشتق منه بـ Argon2id بملح هذا الجهاز. جذور الخزنات الأخرى مشتقة بنفس
+// الطريقة، فحذف هذا الملف يقطع الطريق إليها - يُنسخ احتياطياً معها.
+// الصيغ الأقدم بنفس الاسم تُقرأ وتُحوَّل عند أول حفظ: بلا مُحقِّق (رموز الإتلاف وحدها، قبل
+// رمز الفتح القابل للتغيير) وبمُحقِّق وسجلات إتلاف بلا وسوم. أي طول آخر ملف تالف يُرفض ولا
+// يُستبدل، لأن ملحاً جديداً ينقل جذور كل الخزنات الأخرى
+const CODES_FILE: &str = "prefs";
+pub(super) const SALT_LEN: usize = 16;
+const VERIFIER_LEN: usize = 32;
+pub(super) const DURESS_OFFSET: usize = SALT_LEN + VERIFIER_LEN;
+const TABLE_LEN: usize = DURESS_OFFSET + DURESS_TABLE_LEN;
+const DURESS_ONLY_LEN: usize = SALT_LEN + LEGACY_DURESS_LEN;
+const UNTAGGED_LEN: usize = DURESS_OFFSET + LEGACY_DURESS_LEN;
+
+// أخف من معاملات فتح الخزنة لأنها تُحسب مع كل "=" في الحاسبة
+const CODE_KDF: KdfParams = KdfParams { m_cost: 19 * 1024, t_cost: 2, p_cost: 1 };
+
+pub(super) struct CodeTable {
+    path: PathBuf,
+    pub(super) bytes: Vec<u8>,
+    // رمز الفتح مضبوط - ملف الصيغة الأقدم بلا مُحقِّق يحمل رموز إتلاف فقط
+    pub(super) configured: bool,
+}
+
+impl CodeTable {
+    pub(super) fn default_path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("calculator").join(CODES_FILE))
+    }
+
+    // None إن لم يوجد الملف بعد
+    pub(super) fn load(path: &Path) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let old = match fs::read(path) {
+            Ok(old) => old,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("تعذر قراءة ملف الرموز: {}", e).into()),
+        };
+        let (bytes, configured) = match old.len() {
+            TABLE_LEN => (old, true),
+            DURESS_ONLY_LEN => (Self::migrate(&old[..SALT_LEN], None, &old[SALT_LEN..], true), false),
+            UNTAGGED_LEN => {
+                let verifier = &old[SALT_LEN..DURESS_OFFSET];
+                (Self::migrate(&old[..SALT_LEN], Some(verifier), &old[DURESS_OFFSET..], false), true)
+            }
+            _ => return Err(format!(
+                "ملف الرموز تالف ({}) - استرجعه من النسخة الاحتياطية، فاستبداله يقطع الطريق إلى الخزنات الأخرى",
+                path.display()
+            ).into()),
+        };
+        Ok(Some(CodeTable { path: path.to_path_buf(), bytes, configured }))
+    }
+
+    // الملح يبقى كما هو فلا تتغير جذور الخزنات ولا رموزها
+    fn migrate(salt: &[u8], verifier: Option<&[u8]>, records: &[u8], derived_from_value: bool) -> Vec<u8> {
+        let mut bytes = vec![0u8; TABLE_LEN];
+        OsRng.fill_bytes(&mut bytes);
+        bytes[..SALT_LEN].copy_from_slice(salt);
+        if let Some(verifier) = verifier {
+            bytes[SALT_LEN..DURESS_OFFSET].copy_from_slice(verifier);
+        }
+        duress::migrate_records(&mut bytes, records, derived_from_value);
+        bytes
+    }
+
+    // جدول جديد ببايتات عشوائية: مُحقِّق لا يطابق أي رمز وسجلات إتلاف فارغة (انظر duress.rs)
+    pub(super) fn load_or_create(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::load(path)?.unwrap_or_else(|| {
+            let mut bytes = vec![0u8; TABLE_LEN];
+            OsRng.fill_bytes(&mut bytes);
+            duress::init_records(&mut bytes);
+            CodeTable { path: path.to_path_buf(), bytes, configured: false }
+        }))
+    }
+
+    pub(super) fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&self.path, &self.bytes)?;
+        Ok(())
+    }
+
+    pub(super) fn derive(&self, code: &str) -> Result<CodeKey, Box<dyn std::error::Error>> {
+        derive_key(code.as_bytes(), &self.bytes[..SALT_LEN], &CODE_KDF).map(CodeKey)
+    }
+}
+
+// ما يُشتق من رمز واحد - ولكل استخدام مفتاح فرعي مستقل
+pub(super) struct CodeKey(SecretKey);
+
+impl CodeKey {
+    pub(super) fn subkey(&self, label: &[u8]) -> SecretKey {
+        derive_subkey(&self.0, label)
+    }
+
+    // جذر الخزنة التي يفتحها هذا الرمز - اسمه لا يحمل أي إشارة إلى التطبيق، وبين جذور الخزنات
+    // جذور وهمية بنفس الشكل (انظر decoy.rs)
+    fn vault_root(&self) -> Option<PathBuf> {
+        let name = hex::encode(&self.subkey(b"calculator-vault/root")[..8]);
+        Some(dirs::data_local_dir()?.join(format!(".{}", name)))
+    }
+
+    // سجل محاولات الفتح لما يفتحه هذا الرمز (انظر attempts.rs)
+    fn attempts_key(&self) -> AttemptsKey {
+        AttemptsKey::new(self.subkey(b"calculator-vault/attempts"))
+    }
+}
+
+// مقارنة بزمن ثابت
+pub(super) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// الإعداد الأول لم يتم بعد: لا رمز فتح، والحاسبة لا تفتح شيئاً. ملف تالف خطأ لا إعداد أول
+pub fn codes_configured() -> Result<bool, Box<dyn std::error::Error>> {
+    let path = CodeTable::default_path().ok_or("مجلد الإعدادات غير متاح")?;
+    Ok(CodeTable::load(&path)?.is_some_and(|table| table.configured))
+}
+
+// الضبط الأول يزرع الجذور الوهمية قبل أن توجد أي خزنة أخرى
+pub fn set_unlock_code(code: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let first = !codes_configured()?;
+    set_unlock_code_in(&CodeTable::default_path().ok_or("مجلد الإعدادات غير متاح")?, code)?;
+    if first {
+        plant_decoys(2, 5)?;
+    }
+    Ok(())
+}
+
+pub(super) fn set_unlock_code_in(path: &Path, code: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut table = CodeTable::load_or_create(path)?;
+    let verifier = table.derive(code)?.subkey(b"calculator-vault/unlock");
+    table.bytes[SALT_LEN..DURESS_OFFSET].copy_from_slice(&*verifier);
+    table.configured = true;
+    table.save()
+}
+
+// جذر خزنة أخرى لرمز جديد ومفتاح سجل محاولاتها - يُستخدم عند إنشائها. جذور وهمية تُزرع معها
+// فلا يكون المجلد الجديد الوحيد بهذا الوقت
+pub fn code_vault_dir(code: &str) -> Result<(PathBuf, AttemptsKey), Box<dyn std::error::Error>> {
+    let path = CodeTable::default_path().ok_or("مجلد الإعدادات غير متاح")?;
+    let table = CodeTable::load(&path)?.filter(|table| table.configured).ok_or("لم يُضبط رمز الفتح بعد")?;
+    let key = table.derive(code)?;
+    let root = key.vault_root().ok_or("مجلد بيانات التطبيق غير متاح")?;
+    plant_decoys(0, 2)?;
+    Ok((root, key.attempts_key()))
+}
+
+// كل خزنة تُعطى مع مفتاح سجل محاولاتها المشتق من الرمز الذي طابق
+pub enum CodeMatch {
+    None,
+    // رمز الفتح: الخزنة في المجلد المعروف
+    Unlock(AttemptsKey),
+    // رمز خزنة أخرى موجودة
+    Vault(PathBuf, AttemptsKey),
+    Duress(DuressMatch, AttemptsKey),
+}
+
+// يُستدعى مع كل "=" بكل صيغ الإدخال المرشحة (النتيجة والعملية). كل الفحوص تجري لكل صيغة
+// مهما كانت النتيجة حتى لا يكشف الزمن نوع الرمز
+pub fn match_code(candidates: &[&str]) -> CodeMatch {
+    match CodeTable::default_path() {
+        Some(path) => match_code_in(&path, candidates),
+        None => CodeMatch::None,
+    }
+}
+
+pub(super) fn match_code_in(path: &Path, candidates: &[&str]) -> CodeMatch {
+    let Ok(Some(table)) = CodeTable::load(path) else { return CodeMatch::None };
+    let value_records = duress::has_value_records(&table);
+    let (mut duress, mut unlock, mut root) = (None, None, None);
+    for candidate in candidates {
+        let Ok(key) = table.derive(candidate) else { continue };
+        // رموز الإتلاف المحوَّلة من الصيغة الأولى مشتقة من النتيجة وحدها
+        let value_key = candidate.strip_prefix("value:").filter(|_| value_records)
+            .and_then(|value| table.derive(value).ok());
+        let found = duress::find(&table, &key, value_key.as_ref());
+        let matched = table.configured
+            && constant_time_eq(&*key.subkey(b"calculator-vault/unlock"), &table.bytes[SALT_LEN..DURESS_OFFSET]);
+        let found_root = key.vault_root().filter(|dir| VaultHeader::load(dir).is_some());
+        let attempts = key.attempts_key();
+        duress = duress.or(found.map(|found| (found, attempts.clone())));
+        unlock = unlock.or(matched.then(|| attempts.clone()));
+        root = root.or(found_root.map(|root| (root, attempts)));
+    }
+
+    // رمز الإتلاف يسبق دائماً حتى لو طابقت صيغة أخرى من نفس الإدخال رمز فتح
+    match (duress, unlock, root) {
+        (Some((duress, attempts)), _, _) => CodeMatch::Duress(duress, attempts),
+        (None, Some(attempts), _) => CodeMatch::Unlock(attempts),
+        (None, None, Some((root, attempts))) => CodeMatch::Vault(root, attempts),
+        (None, None, None) => CodeMatch::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::temp_dir;
+
+    #[test]
+    fn unlock_code_accepts_expression_and_replaces_old() {
+        let dir = temp_dir("unlock");
+        let table = dir.join("prefs");
+        set_unlock_code_in(&table, "expr:1337*3").unwrap();
+        let bytes = fs::read(&table).unwrap();
+        assert!(!bytes.windows(6).any(|w| w == b"1337*3"));
+
+        assert!(matches!(match_code_in(&table, &["expr:1337*3"]), CodeMatch::Unlock(_)));
+        // نفس النتيجة بعملية أخرى لا تفتح
+        assert!(matches!(match_code_in(&table, &["value:4011"]), CodeMatch::None));
+
+        set_unlock_code_in(&table, "value:4011").unwrap();
+        assert!(matches!(match_code_in(&table, &["expr:1337*3"]), CodeMatch::None));
+        assert!(matches!(match_code_in(&table, &["value:4011"]), CodeMatch::Unlock(_)));
+        // الملح لا يتغير فلا تضيع الخزنات الأخرى المشتقة منه
+        assert_eq!(fs::read(&table).unwrap()[..SALT_LEN], bytes[..SALT_LEN]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn malformed_table_is_kept_and_rejected() {
+        let dir = temp_dir("malformed");
+        let table = dir.join("prefs");
+        set_unlock_code_in(&table, "value:49").unwrap();
+        let mut bytes = fs::read(&table).unwrap();
+        bytes.truncate(TABLE_LEN - 1);
+        fs::write(&table, &bytes).unwrap();
+
+        assert!(CodeTable::load(&table).is_err());
+        assert!(set_unlock_code_in(&table, "value:50").is_err());
+        assert!(matches!(match_code_in(&table, &["value:49"]), CodeMatch::None));
+        assert_eq!(fs::read(&table).unwrap(), bytes);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
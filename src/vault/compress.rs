@@ -0,0 +1,111 @@
+use std::io::{self, Read, Write};
+use zstd::stream::raw::Decoder;
+use zstd::stream::zio::Writer;
+
+// ضغط zstd اختياري قبل التشفير. الحمولة المضغوطة إطار zstd عادي، ويُفك ضغطها بشفافية
+// عند فك التشفير حسب علم FLAG_COMPRESSED في ترويسة الحاوية الموثّقة
+const LEVEL: i32 = 3;
+
+// ما يُقرأ من بداية الملف للتعرف على نوعه قبل قرار الضغط
+pub const SNIFF_LEN: usize = 64;
+
+// صيغ مضغوطة أصلاً - ضغطها مرة أخرى يضيع الوقت ولا يوفر شيئاً. التعرف بالمحتوى لا بالامتداد
+pub fn is_compressible(sample: &[u8]) -> bool {
+    const SIGNATURES: &[&[u8]] = &[
+        b"\xFF\xD8\xFF",             // JPEG
+        b"\x89PNG\r\n\x1A\n",        // PNG
+        b"GIF8",                     // GIF
+        b"PK\x03\x04",               // ZIP (ومعه docx و xlsx و apk...)
+        b"PK\x05\x06",               // ZIP فارغ
+        b"\x1F\x8B",                 // gzip
+        b"\x28\xB5\x2F\xFD",         // zstd
+        b"7z\xBC\xAF\x27\x1C",       // 7z
+        b"Rar!\x1A\x07",             // RAR
+        b"BZh",                      // bzip2
+        b"\xFD7zXZ\x00",             // xz
+        b"\x1A\x45\xDF\xA3",         // Matroska / WebM
+        b"OggS",                     // Ogg
+        b"fLaC",                     // FLAC
+        b"ID3",                      // MP3 بوسوم ID3
+    ];
+    if SIGNATURES.iter().any(|sig| sample.starts_with(sig)) {
+        return false;
+    }
+    // MP4 و MOV و HEIC و M4A: صندوق ftyp بعد حقل الطول
+    if sample.len() >= 8 && &sample[4..8] == b"ftyp" {
+        return false;
+    }
+    // WebP و AVI داخل حاوية RIFF (أما WAV فيُضغط)
+    if sample.len() >= 12 && sample.starts_with(b"RIFF") && matches!(&sample[8..12], b"WEBP" | b"AVI ") {
+        return false;
+    }
+    // إطار MPEG صوتي بلا وسوم
+    if sample.len() >= 2 && sample[0] == 0xFF && sample[1] & 0xE0 == 0xE0 {
+        return false;
+    }
+    true
+}
+
+pub fn compress_reader<R: Read>(reader: R) -> io::Result<impl Read> {
+    zstd::stream::read::Encoder::new(reader, LEVEL)
+}
+
+// فك الضغط أثناء الكتابة إلى الوجهة - لا يُجمع الملف في الذاكرة
+pub struct DecompressWriter<W: Write> {
+    inner: Writer<W, Decoder<'static>>,
+}
+
+impl<W: Write> DecompressWriter<W> {
+    pub fn new(inner: W) -> io::Result<Self> {
+        Ok(DecompressWriter { inner: Writer::new(inner, Decoder::new()?) })
+    }
+
+    // إطار ناقص يعني حمولة لا تطابق ما ضُغط - الملف تالف
+    pub fn finish(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.inner.finish().map_err(|_| "خطأ في فك التشفير: تعذر فك ضغط الملف")?;
+        self.inner.writer_mut().flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for DecompressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_formats_are_skipped() {
+        assert!(!is_compressible(b"\xFF\xD8\xFF\xE0\x00\x10JFIF"));
+        assert!(!is_compressible(b"\x00\x00\x00\x18ftypmp42\x00\x00\x00\x00"));
+        assert!(!is_compressible(b"PK\x03\x04\x14\x00\x06\x00"));
+        assert!(is_compressible(b"plain text compresses well"));
+        // أقصر من أن يحمل صندوق ftyp
+        assert!(is_compressible(b"ftyp"));
+    }
+
+    #[test]
+    fn truncated_frame_is_rejected() {
+        let data = vec![b'a'; 10_000];
+        let mut compressed = Vec::new();
+        compress_reader(&data[..]).unwrap().read_to_end(&mut compressed).unwrap();
+
+        let mut plain = Vec::new();
+        let mut writer = DecompressWriter::new(&mut plain).unwrap();
+        writer.write_all(&compressed).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(plain, data);
+
+        let mut writer = DecompressWriter::new(Vec::new()).unwrap();
+        writer.write_all(&compressed[..compressed.len() - 1]).unwrap();
+        assert!(writer.finish().is_err());
+    }
+}
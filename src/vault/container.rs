@@ -0,0 +1,259 @@
+use aes_gcm::aead::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use super::cipher::CipherKind;
+use super::stream::CHUNK_SIZE;
+
+// صيغة ملف .secure ذاتية الوصف:
+//
+//   magic "CVLT" | version u8 | cipher u8 (انظر CipherKind) | kdf u8 | flags u8
+//   kdf_params_len u16 | kdf_params
+//   chunk_size u32 | metadata_len u32 | metadata (nonce || ciphertext)
+//   ثم جسم STREAM
+//
+// الأعداد بترتيب big-endian. البيانات المرفقة لكل قطعة = الترويسة كاملة || هوية الملف
+// (المعرّف والاسم والامتداد)، فلا يُفك ملف إلا مع إدخال الفهرس الذي أنشئ له
+pub const MAGIC: &[u8; 4] = b"CVLT";
+// الإصدار 1 لا يحمل بايت الأعلام ويُقرأ كما هو
+pub const FORMAT_VERSION: u8 = 2;
+
+// الحمولة محشوة (انظر PaddingPolicy) وتبدأ بالطول الحقيقي
+pub const FLAG_PADDED: u8 = 0b0000_0001;
+// الحمولة مضغوطة بـ zstd قبل التشفير (وحشوها إن وُجد إطارات قابلة للتخطي)
+pub const FLAG_COMPRESSED: u8 = 0b0000_0010;
+
+// مفتاح البيانات عشوائي ومغلف بالمفتاح الرئيسي في الفهرس - لا معاملات اشتقاق
+pub const KDF_WRAPPED_DATA_KEY: u8 = 1;
+
+const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+const MAX_METADATA_LEN: u32 = 64 * 1024;
+
+// بيانات وصفية مشفرة داخل الملف نفسه حتى لا يعتمد التعرف عليه على الفهرس وحده
+#[derive(Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub file_id: String,
+    pub name: String,
+    pub original_extension: String,
+    pub created_at: String,
+}
+
+pub struct ContainerHeader {
+    pub version: u8,
+    pub cipher: CipherKind,
+    pub kdf: u8,
+    pub flags: u8,
+    pub kdf_params: Vec<u8>,
+    pub chunk_size: u32,
+    pub metadata: Vec<u8>,
+}
+
+// شكل الملف على القرص: الحاوية الجديدة أو الصيغة القديمة (nonce من 12 بايت || ciphertext)
+pub enum Layout {
+    Container(ContainerHeader),
+    Legacy,
+}
+
+impl ContainerHeader {
+    pub fn new(cipher: CipherKind, flags: u8, data_key: &[u8; 32], metadata: &FileMetadata) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(ContainerHeader {
+            version: FORMAT_VERSION,
+            cipher,
+            kdf: KDF_WRAPPED_DATA_KEY,
+            flags,
+            kdf_params: Vec::new(),
+            chunk_size: CHUNK_SIZE as u32,
+            metadata: cipher.backend().seal(data_key, b"", &serde_json::to_vec(metadata)?)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(20 + self.kdf_params.len() + self.metadata.len());
+        out.extend_from_slice(MAGIC);
+        out.push(self.version);
+        out.push(self.cipher.id());
+        out.push(self.kdf);
+        if self.version >= 2 {
+            out.push(self.flags);
+        }
+        out.extend_from_slice(&(self.kdf_params.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.kdf_params);
+        out.extend_from_slice(&self.chunk_size.to_be_bytes());
+        out.extend_from_slice(&(self.metadata.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.metadata);
+        out
+    }
+
+    // يقرأ الترويسة إن وجدت، وإلا يعيد المؤشر لبداية الملف لقراءته بالصيغة القديمة
+    pub fn detect(reader: &mut (impl Read + Seek)) -> Result<Layout, Box<dyn std::error::Error>> {
+        let mut magic = [0u8; 4];
+        if reader.read_exact(&mut magic).is_err() || &magic != MAGIC {
+            reader.seek(SeekFrom::Start(0))?;
+            return Ok(Layout::Legacy);
+        }
+
+        let version = read_u8(reader)?;
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(format!("إصدار صيغة غير مدعوم: {}", version).into());
+        }
+        let cipher = CipherKind::from_id(read_u8(reader)?)?;
+        let kdf = read_u8(reader)?;
+        if kdf != KDF_WRAPPED_DATA_KEY {
+            return Err(format!("طريقة اشتقاق غير مدعومة: {}", kdf).into());
+        }
+        let flags = if version >= 2 { read_u8(reader)? } else { 0 };
+
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        let kdf_params = read_vec(reader, u16::from_be_bytes(len) as usize)?;
+
+        let chunk_size = read_u32(reader)?;
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err("حجم قطعة غير صالح".into());
+        }
+        let metadata_len = read_u32(reader)?;
+        if metadata_len > MAX_METADATA_LEN {
+            return Err("ترويسة الملف تالفة".into());
+        }
+        let metadata = read_vec(reader, metadata_len as usize)?;
+
+        Ok(Layout::Container(ContainerHeader { version, cipher, kdf, flags, kdf_params, chunk_size, metadata }))
+    }
+
+    pub fn open_metadata(&self, data_key: &[u8; 32]) -> Result<FileMetadata, Box<dyn std::error::Error>> {
+        let plain = self.cipher.backend().open(data_key, b"", &self.metadata)
+            .map_err(|_| "تعذر فك البيانات الوصفية للملف")?;
+        Ok(serde_json::from_slice(&plain)?)
+    }
+
+    pub fn write_body(&self, data_key: &[u8; 32], identity: &[u8], mut reader: impl Read, mut writer: impl Write) -> Result<u64, Box<dyn std::error::Error>> {
+        let header = self.to_bytes();
+        writer.write_all(&header)?;
+        let aad = body_aad(&header, identity);
+        self.cipher.backend().encrypt_stream(data_key, self.chunk_size as usize, &aad, &mut reader, &mut writer)
+    }
+
+    pub fn read_body(&self, data_key: &[u8; 32], identity: &[u8], mut reader: impl Read, mut writer: impl Write) -> Result<u64, Box<dyn std::error::Error>> {
+        let aad = body_aad(&self.to_bytes(), identity);
+        self.cipher.backend().decrypt_stream(data_key, self.chunk_size as usize, &aad, &mut reader, &mut writer)
+    }
+}
+
+pub fn generate_file_id() -> String {
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut id);
+    hex::encode(id)
+}
+
+// هوية الملف كما يراها الفهرس، بحقول مسبوقة بطولها حتى لا يلتبس حد بين حقلين
+pub fn file_identity(file_id: &str, name: &str, extension: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    for field in [file_id, name, extension] {
+        out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        out.extend_from_slice(field.as_bytes());
+    }
+    out
+}
+
+fn body_aad(header: &[u8], identity: &[u8]) -> Vec<u8> {
+    let mut aad = header.to_vec();
+    aad.extend_from_slice(identity);
+    aad
+}
+
+fn read_u8(reader: &mut impl Read) -> std::io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn read_vec(reader: &mut impl Read, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const DATA_KEY: [u8; 32] = [3u8; 32];
+
+    fn metadata() -> FileMetadata {
+        FileMetadata {
+            file_id: generate_file_id(),
+            name: "report".to_string(),
+            original_extension: "pdf".to_string(),
+            created_at: "2024-01-01".to_string(),
+        }
+    }
+
+    fn seal(cipher: CipherKind, identity: &[u8], data: &[u8]) -> Vec<u8> {
+        let header = ContainerHeader::new(cipher, FLAG_PADDED, &DATA_KEY, &metadata()).unwrap();
+        let mut file = Vec::new();
+        header.write_body(&DATA_KEY, identity, data, &mut file).unwrap();
+        file
+    }
+
+    fn open(file: &[u8], identity: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut reader = Cursor::new(file);
+        let Layout::Container(header) = ContainerHeader::detect(&mut reader)? else {
+            return Err("ليست حاوية".into());
+        };
+        let mut plain = Vec::new();
+        header.read_body(&DATA_KEY, identity, reader, &mut plain)?;
+        Ok(plain)
+    }
+
+    #[test]
+    fn header_and_body_roundtrip() {
+        let identity = file_identity("id", "report", "pdf");
+        let data = vec![5u8; CHUNK_SIZE + 100];
+        for cipher in CipherKind::ALL {
+            let file = seal(cipher, &identity, &data);
+            assert_eq!(open(&file, &identity).unwrap(), data);
+
+            let Layout::Container(header) = ContainerHeader::detect(&mut Cursor::new(&file)).unwrap() else {
+                panic!("ليست حاوية");
+            };
+            assert!(header.cipher == cipher);
+            assert_eq!(header.version, FORMAT_VERSION);
+            assert_eq!(header.flags, FLAG_PADDED);
+            assert_eq!(header.open_metadata(&DATA_KEY).unwrap().name, "report");
+            assert!(header.open_metadata(&[4u8; 32]).is_err());
+        }
+    }
+
+    #[test]
+    fn header_and_identity_are_authenticated() {
+        let identity = file_identity("id", "report", "pdf");
+        let file = seal(CipherKind::default(), &identity, b"secret");
+
+        // بايت الأعلام جزء من البيانات المرفقة لكل قطعة
+        let mut flags = file.clone();
+        flags[7] ^= FLAG_COMPRESSED;
+        assert!(open(&flags, &identity).is_err());
+
+        // نفس الحاوية تحت اسم آخر في الفهرس لا تُفك
+        assert!(open(&file, &file_identity("id", "report2", "pdf")).is_err());
+
+        let mut version = file.clone();
+        version[4] = FORMAT_VERSION + 1;
+        assert!(open(&version, &identity).is_err());
+    }
+
+    #[test]
+    fn legacy_files_rewind() {
+        let mut reader = Cursor::new(vec![1u8; 40]);
+        assert!(matches!(ContainerHeader::detect(&mut reader).unwrap(), Layout::Legacy));
+        assert_eq!(reader.position(), 0);
+    }
+}
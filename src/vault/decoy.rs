@@ -0,0 +1,120 @@
+use aes_gcm::aead::OsRng;
+use rand::{Rng, RngCore};
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::cipher::CipherKind;
+use super::container::{file_identity, generate_file_id, ContainerHeader, FileMetadata};
+use super::header::VaultHeader;
+use super::index::{content_mac, write_index, EncryptedFile, KeyScheme};
+use super::keys::{generate_key, wrap_key};
+
+// جذور خزنات وهمية بجانب جذور الخزنات الأخرى، بنفس شكل الاسم: ترويسة بفتحة كلمة مرور لا تفتحها
+// أي كلمة، وفهرس وحاويات مشفرة بمفاتيح عشوائية أُسقطت فور الكتابة. بضعة منها تُزرع عند ضبط رمز
+// الفتح أول مرة، وعدد عشوائي آخر مع كل خزنة جديدة، فلا يدل عدد المجلدات على عدد الخزنات ولا
+// يدل مجلد جديد على خزنة جديدة
+const MAX_FILES: u32 = 6;
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+const EXTENSIONS: [&str; 6] = ["jpg", "png", "pdf", "mp4", "docx", "txt"];
+
+// عدد عشوائي بين least و most
+pub(super) fn plant_decoys(least: u32, most: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let parent = dirs::data_local_dir().ok_or("مجلد بيانات التطبيق غير متاح")?;
+    for _ in 0..OsRng.gen_range(least..=most) {
+        let mut name = [0u8; 8];
+        OsRng.fill_bytes(&mut name);
+        plant_decoy(&parent.join(format!(".{}", hex::encode(name))))?;
+    }
+    Ok(())
+}
+
+fn plant_decoy(root: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let cipher = CipherKind::ALL[OsRng.gen_range(0..CipherKind::ALL.len())];
+    VaultHeader::decoy(cipher)?.save(root)?;
+    if OsRng.gen_bool(0.5) {
+        fs::create_dir_all(root.join("upload"))?;
+    }
+
+    let master_key = generate_key();
+    let files = (0..OsRng.gen_range(0..=MAX_FILES))
+        .map(|_| plant_container(root, cipher, &master_key))
+        .collect::<Result<Vec<_>, _>>()?;
+    // رقم الجيل ظاهر في ترويسة الفهرس - خزنة مستعملة حفظت فهرسها مرات كثيرة
+    let generation = files.len() as u64 + OsRng.gen_range(1..=40);
+    write_index(root, &master_key, &files, generation)
+}
+
+// حاوية بالصيغة الحالية لمحتوى من أصفار بمفتاح بيانات عشوائي - لا تختلف عن حاوية ملف حقيقي
+fn plant_container(root: &Path, cipher: CipherKind, master_key: &[u8; 32]) -> Result<EncryptedFile, Box<dyn std::error::Error>> {
+    let data_key = generate_key();
+    let file_id = generate_file_id();
+    let mut stem = [0u8; 6];
+    OsRng.fill_bytes(&mut stem);
+    let extension = EXTENSIONS[OsRng.gen_range(0..EXTENSIONS.len())].to_string();
+    let name = format!("{}.{}", hex::encode(stem), extension);
+    let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+    let header = ContainerHeader::new(cipher, 0, &data_key, &FileMetadata {
+        file_id: file_id.clone(),
+        name: name.clone(),
+        original_extension: extension.clone(),
+        created_at: created_at.clone(),
+    })?;
+
+    let encrypted_path = root.join(format!("{:x}.secure", OsRng.next_u64()));
+    let size = OsRng.gen_range(4096..=MAX_FILE_SIZE);
+    let mut output = BufWriter::new(fs::File::create(&encrypted_path)?);
+    header.write_body(&data_key, &file_identity(&file_id, &name, &extension), io::repeat(0).take(size), &mut output)?;
+    output.flush()?;
+
+    let mut sha256 = [0u8; 32];
+    OsRng.fill_bytes(&mut sha256);
+    let sha256 = hex::encode(sha256);
+    Ok(EncryptedFile {
+        file_id,
+        name,
+        original_extension: extension,
+        encrypted_path: encrypted_path.to_string_lossy().to_string(),
+        size,
+        created_at,
+        scheme: KeyScheme::Envelope,
+        wrapped_key: wrap_key(master_key, &data_key)?,
+        content_mac: content_mac(master_key, &sha256),
+        sha256,
+        blob_name: None,
+        migration_error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::container::Layout;
+    use super::super::slots::Credential;
+    use super::super::temp_dir;
+
+    #[test]
+    fn decoy_looks_like_a_vault_and_opens_with_nothing() {
+        let dir = temp_dir("decoy");
+        let (decoy, real) = (dir.join("decoy"), dir.join("real"));
+        plant_decoy(&decoy).unwrap();
+        VaultHeader::create(&real, "password", CipherKind::default()).unwrap();
+
+        let (decoy_header, real_header) = (VaultHeader::load(&decoy).unwrap(), VaultHeader::load(&real).unwrap());
+        assert_eq!(decoy_header.slots.len(), 1);
+        assert_eq!(decoy_header.slots[0].wrapped_key.len(), real_header.slots[0].wrapped_key.len());
+        assert_eq!(decoy_header.slots[0].salt.len(), real_header.slots[0].salt.len());
+        assert!(decoy_header.unlock(&Credential::Secret("password")).is_err());
+
+        // كل حاوية في المجلد بالصيغة الحالية
+        for entry in fs::read_dir(&decoy).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "secure") {
+                let mut file = fs::File::open(&path).unwrap();
+                assert!(matches!(ContainerHeader::detect(&mut file), Ok(Layout::Container(_))));
+            }
+        }
+        assert!(decoy.join("index.vault").is_file());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,499 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+use super::codes::{constant_time_eq, CodeKey, CodeTable, DURESS_OFFSET, SALT_LEN};
+use super::container::MAGIC;
+use super::header::VaultHeader;
+use super::keys::derive_subkey;
+use super::shred::detach_and_shred;
+
+// سجلات رموز الإتلاف في ملف الرموز (codes.rs) بعد مُحقِّق رمز الفتح:
+//   DURESS_SLOTS × (وسم 16 | مُحقِّق 32 | nonce 12 | الخزنة المستهدفة مشفرة TARGET_LEN + 16)
+// وسم السجل المستخدم مشتق من المفتاح الرئيسي لخزنته فلا يعرفه غيرها، ووسم الموضع الفارغ
+// مشتق من ملح الملف وموضعه فيعرفه الجميع. الجدول الجديد يبدأ ببضعة مواضع مشغولة بعشوائيات
+// لا يملكها أحد، فعدد المواضع المشغولة لا يكشف إن كان هناك رمز إتلاف ولا لأي خزنة.
+// الرمز المُدخل يُقارن بكل السجلات بزمن ثابت.
+// سجلات الصيغ الأقدم (بلا وسوم، وموضعها مختصر مسار الخزنة) تُنقل إلى المواضع الأولى بوسم
+// "قديم" معروف، وتُزال بنفس قاعدة الموضع القديمة عند ضبط رمز الخزنة أو إزالته
+const DURESS_SLOTS: usize = 32;
+const TAG_LEN: usize = 16;
+const TARGET_LEN: usize = 512;
+const SEALED_LEN: usize = 32 + 12 + TARGET_LEN + 16;
+const RECORD_LEN: usize = TAG_LEN + SEALED_LEN;
+pub(super) const DURESS_TABLE_LEN: usize = DURESS_SLOTS * RECORD_LEN;
+const MAX_DECOYS: u32 = 4;
+const LEGACY_SLOTS: usize = 16;
+pub(super) const LEGACY_DURESS_LEN: usize = LEGACY_SLOTS * SEALED_LEN;
+
+// ما يظهر بعد الإتلاف: شاشة كلمة المرور كفتح عادي، أو لا شيء كرمز خاطئ
+#[derive(Clone, Copy, PartialEq)]
+pub enum DuressMode {
+    ShowUnlock,
+    StayOnCalculator,
+}
+
+pub struct DuressMatch {
+    pub vault_dir: PathBuf,
+    pub mode: DuressMode,
+}
+
+fn table_path() -> Option<PathBuf> {
+    CodeTable::default_path()
+}
+
+fn record_range(slot: usize) -> std::ops::Range<usize> {
+    let start = DURESS_OFFSET + slot * RECORD_LEN;
+    start..start + RECORD_LEN
+}
+
+fn public_tag(label: &[u8], bytes: &[u8], slot: usize) -> [u8; TAG_LEN] {
+    let mut hasher = Sha256::new();
+    hasher.update(label);
+    hasher.update(&bytes[..SALT_LEN]);
+    hasher.update((slot as u32).to_be_bytes());
+    hasher.finalize()[..TAG_LEN].try_into().expect("SHA-256 أطول من الوسم")
+}
+
+fn free_tag(bytes: &[u8], slot: usize) -> [u8; TAG_LEN] {
+    public_tag(b"calculator-vault/duress-free", bytes, slot)
+}
+
+// سجل قديم: مشتق من الرمز بصيغته الحالية، أو من النتيجة وحدها (قبل رمز الفتح القابل للتغيير)
+fn legacy_tag(bytes: &[u8], slot: usize, derived_from_value: bool) -> [u8; TAG_LEN] {
+    let label: &[u8] = if derived_from_value {
+        b"calculator-vault/duress-legacy-value"
+    } else {
+        b"calculator-vault/duress-legacy"
+    };
+    public_tag(label, bytes, slot)
+}
+
+fn owner_tag(bytes: &[u8], master_key: &[u8; 32]) -> [u8; TAG_LEN] {
+    let key = derive_subkey(master_key, b"calculator-vault/duress-owner");
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&*key).expect("HMAC يقبل أي طول مفتاح");
+    mac.update(&bytes[..SALT_LEN]);
+    mac.finalize().into_bytes()[..TAG_LEN].try_into().expect("HMAC أطول من الوسم")
+}
+
+fn tag_of(bytes: &[u8], slot: usize) -> &[u8] {
+    &bytes[record_range(slot)][..TAG_LEN]
+}
+
+fn free_slot(bytes: &mut [u8], slot: usize) {
+    let tag = free_tag(bytes, slot);
+    let record = &mut bytes[record_range(slot)];
+    OsRng.fill_bytes(record);
+    record[..TAG_LEN].copy_from_slice(&tag);
+}
+
+// سجلات جدول جديد: كلها فارغة عدا مواضع عشوائية تبقى مشغولة بعشوائيات
+pub(super) fn init_records(bytes: &mut [u8]) {
+    for slot in 0..DURESS_SLOTS {
+        free_slot(bytes, slot);
+    }
+    let decoys = 1 + OsRng.next_u32() % MAX_DECOYS;
+    for _ in 0..decoys {
+        let slot = OsRng.next_u32() as usize % DURESS_SLOTS;
+        OsRng.fill_bytes(&mut bytes[record_range(slot)]);
+    }
+}
+
+pub(super) fn migrate_records(bytes: &mut [u8], records: &[u8], derived_from_value: bool) {
+    for slot in 0..DURESS_SLOTS {
+        free_slot(bytes, slot);
+    }
+    for (slot, old) in records.chunks_exact(SEALED_LEN).enumerate() {
+        let tag = legacy_tag(bytes, slot, derived_from_value);
+        let record = &mut bytes[record_range(slot)];
+        record[..TAG_LEN].copy_from_slice(&tag);
+        record[TAG_LEN..].copy_from_slice(old);
+    }
+}
+
+pub(super) fn has_value_records(table: &CodeTable) -> bool {
+    (0..LEGACY_SLOTS).any(|slot| tag_of(&table.bytes, slot) == legacy_tag(&table.bytes, slot, true))
+}
+
+// السجل القديم لهذه الخزنة كان في موضع يحدده مسارها - يُفرَّغ عند ضبط رمزها أو إزالته
+fn release_legacy(bytes: &mut [u8], vault_dir: &Path) {
+    let canonical = fs::canonicalize(vault_dir).unwrap_or_else(|_| vault_dir.to_path_buf());
+    let mut hasher = Sha256::new();
+    hasher.update(b"calculator-vault/duress");
+    hasher.update(canonical.to_string_lossy().as_bytes());
+    let slot = hasher.finalize()[0] as usize % LEGACY_SLOTS;
+    let tag = tag_of(bytes, slot);
+    if tag == legacy_tag(bytes, slot, true) || tag == legacy_tag(bytes, slot, false) {
+        free_slot(bytes, slot);
+    }
+}
+
+// مواضع هذه الخزنة - تُفرَّغ قبل كل ضبط وعند الإزالة
+fn owned_slots(bytes: &[u8], master_key: &[u8; 32]) -> Vec<usize> {
+    let owner = owner_tag(bytes, master_key);
+    (0..DURESS_SLOTS).filter(|&slot| tag_of(bytes, slot) == owner).collect()
+}
+
+// مُحقِّق الرمز ومفتاح تشفير الهدف - كلاهما من اشتقاق الرمز نفسه
+fn code_keys(key: &CodeKey) -> Result<([u8; 32], Aes256Gcm), Box<dyn std::error::Error>> {
+    let verifier: [u8; 32] = *key.subkey(b"calculator-vault/duress-verify");
+    let cipher = Aes256Gcm::new_from_slice(&*key.subkey(b"calculator-vault/duress-target"))?;
+    Ok((verifier, cipher))
+}
+
+pub fn set_duress_code(code: &str, vault_dir: &Path, master_key: &[u8; 32], mode: DuressMode) -> Result<(), Box<dyn std::error::Error>> {
+    set_duress_code_in(&table_path().ok_or("مجلد الإعدادات غير متاح")?, code, vault_dir, master_key, mode)
+}
+
+fn set_duress_code_in(
+    path: &Path,
+    code: &str,
+    vault_dir: &Path,
+    master_key: &[u8; 32],
+    mode: DuressMode,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let canonical = fs::canonicalize(vault_dir)?;
+    let target = canonical.to_string_lossy();
+    if target.len() + 3 > TARGET_LEN {
+        return Err("مسار الخزنة أطول من المسموح".into());
+    }
+    let mut table = CodeTable::load(path)?.filter(|table| table.configured).ok_or("لم يُضبط رمز الفتح بعد")?;
+    let (verifier, cipher) = code_keys(&table.derive(code)?)?;
+
+    // الرمز السابق لهذه الخزنة يُزال، ثم يُختار موضع فارغ عشوائي
+    for slot in owned_slots(&table.bytes, master_key) {
+        free_slot(&mut table.bytes, slot);
+    }
+    release_legacy(&mut table.bytes, vault_dir);
+    let free: Vec<usize> = (0..DURESS_SLOTS)
+        .filter(|&slot| tag_of(&table.bytes, slot) == free_tag(&table.bytes, slot))
+        .collect();
+    if free.is_empty() {
+        return Err("لا مكان لرمز إتلاف آخر - أزل رمز الإتلاف من خزنة أخرى أولاً".into());
+    }
+    let slot = free[OsRng.next_u32() as usize % free.len()];
+
+    // mode u8 | الطول u16 | المسار | أصفار حتى TARGET_LEN
+    let mut plain = Zeroizing::new(vec![0u8; TARGET_LEN]);
+    plain[0] = (mode == DuressMode::StayOnCalculator) as u8;
+    plain[1..3].copy_from_slice(&(target.len() as u16).to_be_bytes());
+    plain[3..3 + target.len()].copy_from_slice(target.as_bytes());
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let sealed = cipher.encrypt(&Nonce::from(nonce), plain.as_slice())
+        .map_err(|e| format!("خطأ في تشفير رمز الإتلاف: {:?}", e))?;
+
+    let owner = owner_tag(&table.bytes, master_key);
+    let record = &mut table.bytes[record_range(slot)];
+    record[..TAG_LEN].copy_from_slice(&owner);
+    record[TAG_LEN..TAG_LEN + 32].copy_from_slice(&verifier);
+    record[TAG_LEN + 32..TAG_LEN + 44].copy_from_slice(&nonce);
+    record[TAG_LEN + 44..].copy_from_slice(&sealed);
+    table.save()
+}
+
+// إزالة رمز الإتلاف لهذه الخزنة: موضعها يعود فارغاً
+pub fn clear_duress_code(vault_dir: &Path, master_key: &[u8; 32]) -> Result<(), Box<dyn std::error::Error>> {
+    clear_duress_code_in(&table_path().ok_or("مجلد الإعدادات غير متاح")?, vault_dir, master_key)
+}
+
+fn clear_duress_code_in(path: &Path, vault_dir: &Path, master_key: &[u8; 32]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(mut table) = CodeTable::load(path)? else { return Ok(()) };
+    for slot in owned_slots(&table.bytes, master_key) {
+        free_slot(&mut table.bytes, slot);
+    }
+    release_legacy(&mut table.bytes, vault_dir);
+    table.save()
+}
+
+// وسم السجل مشتق من المفتاح الرئيسي - يُنقل إلى المفتاح الجديد عند تدويره
+pub(super) fn rekey_owner(old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(mut table) = CodeTable::load(&table_path().ok_or("مجلد الإعدادات غير متاح")?)? else { return Ok(()) };
+    let owned = owned_slots(&table.bytes, old_key);
+    if owned.is_empty() {
+        return Ok(());
+    }
+    let owner = owner_tag(&table.bytes, new_key);
+    for slot in owned {
+        table.bytes[record_range(slot)][..TAG_LEN].copy_from_slice(&owner);
+    }
+    table.save()
+}
+
+// يُستدعى من match_code مع كل رمز مُدخل. كل السجلات تُقارن دائماً حتى لا يختلف الزمن بموضع التطابق.
+// value_key اشتقاق النتيجة وحدها، للسجلات المحوَّلة من الصيغة الأولى
+pub(super) fn find(table: &CodeTable, key: &CodeKey, value_key: Option<&CodeKey>) -> Option<DuressMatch> {
+    let current = code_keys(key).ok()?;
+    let legacy = value_key.and_then(|key| code_keys(key).ok());
+
+    let mut matched = None;
+    for slot in 0..DURESS_SLOTS {
+        let record = &table.bytes[record_range(slot)];
+        let (verifier, cipher) = match &legacy {
+            Some(legacy) if record[..TAG_LEN] == legacy_tag(&table.bytes, slot, true) => legacy,
+            _ => &current,
+        };
+        if constant_time_eq(verifier, &record[TAG_LEN..TAG_LEN + 32]) && matched.is_none() {
+            matched = Some((record, cipher));
+        }
+    }
+
+    let (record, cipher) = matched?;
+    let nonce = Nonce::from(<[u8; 12]>::try_from(&record[TAG_LEN + 32..TAG_LEN + 44]).ok()?);
+    let plain = Zeroizing::new(cipher.decrypt(&nonce, &record[TAG_LEN + 44..]).ok()?);
+    let len = u16::from_be_bytes([plain[1], plain[2]]) as usize;
+    let target = std::str::from_utf8(plain.get(3..3 + len)?).ok()?;
+    Some(DuressMatch {
+        vault_dir: PathBuf::from(target),
+        mode: if plain[0] == 1 { DuressMode::StayOnCalculator } else { DuressMode::ShowUnlock },
+    })
+}
+
+// إتلاف الخزنة بمحو المفاتيح لا البيانات: كل فتحة تأخذ ملحاً جديداً ومفتاحاً مغلفاً عشوائياً
+// بنفس الطول، فيضيع المفتاح الرئيسي ومعه مفاتيح كل الملفات. الترويسة تبقى بنفس الشكل
+// والإعدادات (ورمز التحقق الزمني إن وُجد) فتبدو شاشة الفتح كما كانت، وأي كلمة مرور تُرفض.
+// ملفات الصيغة القديمة بالمفتاح الثابت لا تحميها الترويسة فتُحذف فوراً ويُكتب فوقها في الخلفية،
+// فلا يتأخر الرد مهما كان حجمها
+pub fn destroy_vault(vault_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut header = VaultHeader::load(vault_dir).ok_or("الخزنة غير موجودة")?;
+    for slot in header.slots.iter_mut() {
+        slot.scramble();
+    }
+    // الكتابة الذرية وحدها تترك كتل الترويسة القديمة على القرص: الترويسة المتلفة تُكتب فوقها في
+    // مكانها أولاً (بنفس الطول تقريباً لأن الحقول المتلفة بنفس أطوالها)، ثم تُحفظ ذرياً كالمعتاد.
+    // الملف لا يغيب في أي لحظة، فلا تظهر شاشة إنشاء خزنة جديدة حتى لو تعذرت إحدى الخطوتين
+    let scrambled = header.encode()?;
+    let overwritten = overwrite_in_place(&VaultHeader::path(vault_dir), &scrambled);
+    header.save(vault_dir)?;
+    overwritten?;
+
+    let legacy: Vec<PathBuf> = fs::read_dir(vault_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "secure") && !is_container(path))
+        .collect();
+    let _ = detach_and_shred(&legacy, 1);
+    Ok(())
+}
+
+fn overwrite_in_place(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.write_all(data)?;
+    file.set_len(data.len() as u64)?;
+    file.sync_all()
+}
+
+fn is_container(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    fs::File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == MAGIC
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::codes::{match_code_in, set_unlock_code_in, CodeMatch};
+    use super::super::keys::generate_key;
+    use super::super::{open_vault, temp_dir, CipherKind, Credential, SlotKind};
+
+    fn duress_target(table: &Path, code: &str) -> Option<DuressMatch> {
+        match match_code_in(table, &[code]) {
+            CodeMatch::Duress(found, _) => Some(found),
+            _ => None,
+        }
+    }
+
+    // الموضع الذي كان يُختار باختصار المسار قبل الوسوم
+    fn hashed_slot(vault_dir: &Path) -> u8 {
+        let mut hasher = Sha256::new();
+        hasher.update(b"calculator-vault/duress");
+        hasher.update(fs::canonicalize(vault_dir).unwrap().to_string_lossy().as_bytes());
+        hasher.finalize()[0] % 16
+    }
+
+    #[test]
+    fn code_matches_only_its_vault() {
+        let dir = temp_dir("match");
+        let table = dir.join("prefs");
+        let vault = dir.join("vault");
+        fs::create_dir_all(&vault).unwrap();
+        let key = generate_key();
+
+        // بلا إعداد أول لا يوجد ملف ولا يطابق شيء
+        assert!(duress_target(&table, "value:1234").is_none());
+        assert!(!table.exists());
+
+        set_unlock_code_in(&table, "value:49").unwrap();
+        set_duress_code_in(&table, "value:1234", &vault, &key, DuressMode::StayOnCalculator).unwrap();
+        let found = duress_target(&table, "value:1234").unwrap();
+        assert_eq!(found.vault_dir, fs::canonicalize(&vault).unwrap());
+        assert!(found.mode == DuressMode::StayOnCalculator);
+        assert!(duress_target(&table, "value:1235").is_none());
+        assert!(matches!(match_code_in(&table, &["value:49"]), CodeMatch::Unlock(_)));
+
+        // رمز الإتلاف يسبق رمز الفتح مهما كان ترتيب الصيغ المرشحة
+        let candidates = ["value:49", "value:1234"];
+        assert!(matches!(match_code_in(&table, &candidates), CodeMatch::Duress(..)));
+
+        // إعادة الضبط تستبدل الرمز السابق
+        set_duress_code_in(&table, "value:777", &vault, &key, DuressMode::ShowUnlock).unwrap();
+        assert!(duress_target(&table, "value:1234").is_none());
+        assert!(duress_target(&table, "value:777").unwrap().mode == DuressMode::ShowUnlock);
+
+        clear_duress_code_in(&table, &vault, &key).unwrap();
+        assert!(duress_target(&table, "value:777").is_none());
+        assert!(matches!(match_code_in(&table, &["value:49"]), CodeMatch::Unlock(_)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn vaults_with_colliding_hashes_keep_their_codes() {
+        let dir = temp_dir("collide");
+        let table = dir.join("prefs");
+        let first = dir.join("vault-0");
+        fs::create_dir_all(&first).unwrap();
+        let second = (1..)
+            .map(|i| dir.join(format!("vault-{}", i)))
+            .find(|vault| {
+                fs::create_dir_all(vault).unwrap();
+                hashed_slot(vault) == hashed_slot(&first)
+            })
+            .unwrap();
+        let (first_key, second_key) = (generate_key(), generate_key());
+        set_unlock_code_in(&table, "value:49").unwrap();
+
+        set_duress_code_in(&table, "value:111", &first, &first_key, DuressMode::ShowUnlock).unwrap();
+        set_duress_code_in(&table, "value:222", &second, &second_key, DuressMode::StayOnCalculator).unwrap();
+        assert_eq!(duress_target(&table, "value:111").unwrap().vault_dir, fs::canonicalize(&first).unwrap());
+        assert_eq!(duress_target(&table, "value:222").unwrap().vault_dir, fs::canonicalize(&second).unwrap());
+
+        // إزالة رمز خزنة لا تمس رمز الأخرى، ولا تدوير مفتاحها
+        let rotated = generate_key();
+        let mut bytes = CodeTable::load(&table).unwrap().unwrap().bytes;
+        let owned = owned_slots(&bytes, &second_key);
+        let owner = owner_tag(&bytes, &rotated);
+        for slot in owned {
+            bytes[record_range(slot)][..TAG_LEN].copy_from_slice(&owner);
+        }
+        fs::write(&table, &bytes).unwrap();
+        clear_duress_code_in(&table, &first, &first_key).unwrap();
+        assert!(duress_target(&table, "value:111").is_none());
+        assert!(duress_target(&table, "value:222").is_some());
+        clear_duress_code_in(&table, &second, &rotated).unwrap();
+        assert!(duress_target(&table, "value:222").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn full_table_is_an_error() {
+        let dir = temp_dir("full");
+        let table = dir.join("prefs");
+        let vault = dir.join("vault");
+        fs::create_dir_all(&vault).unwrap();
+        set_unlock_code_in(&table, "value:49").unwrap();
+
+        let mut stored = 0;
+        let error = loop {
+            match set_duress_code_in(&table, &format!("value:{}", stored), &vault, &generate_key(), DuressMode::ShowUnlock) {
+                Ok(()) => stored += 1,
+                Err(e) => break e,
+            }
+            assert!(stored <= DURESS_SLOTS);
+        };
+        assert!(error.to_string().contains("لا مكان"));
+        assert!(stored >= DURESS_SLOTS - MAX_DECOYS as usize);
+        // الرموز المحفوظة لم يُكتب فوقها
+        assert!(duress_target(&table, "value:0").is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn older_layouts_keep_their_codes() {
+        let dir = temp_dir("migrate");
+        let current = dir.join("current");
+        let vault = dir.join("vault");
+        fs::create_dir_all(&vault).unwrap();
+        let key = generate_key();
+
+        // سجلات بالصيغة القديمة: الجزء المشفر نفسه في الموضع الذي يحدده مسار الخزنة
+        set_unlock_code_in(&current, "value:49").unwrap();
+        let sealed = |code: &str| {
+            set_duress_code_in(&current, code, &vault, &key, DuressMode::StayOnCalculator).unwrap();
+            let bytes = fs::read(&current).unwrap();
+            let slot = owned_slots(&bytes, &key)[0];
+            bytes[record_range(slot)][TAG_LEN..].to_vec()
+        };
+        let layout = |head: &[u8], record: &[u8]| {
+            let mut bytes = head.to_vec();
+            let mut records = vec![0u8; LEGACY_DURESS_LEN];
+            OsRng.fill_bytes(&mut records);
+            let offset = hashed_slot(&vault) as usize * SEALED_LEN;
+            records[offset..offset + SEALED_LEN].copy_from_slice(record);
+            bytes.extend_from_slice(&records);
+            bytes
+        };
+        let head = fs::read(&current).unwrap()[..DURESS_OFFSET].to_vec();
+
+        // الصيغة الأولى: لا رمز فتح، ورمز الإتلاف مشتق من النتيجة وحدها
+        let first = dir.join("first");
+        fs::write(&first, layout(&head[..SALT_LEN], &sealed("1234"))).unwrap();
+        assert!(!CodeTable::load(&first).unwrap().unwrap().configured);
+        assert!(matches!(match_code_in(&first, &["value:49"]), CodeMatch::None));
+        assert_eq!(duress_target(&first, "value:1234").unwrap().vault_dir, fs::canonicalize(&vault).unwrap());
+        set_unlock_code_in(&first, "value:50").unwrap();
+        let migrated = fs::read(&first).unwrap();
+        assert_eq!(migrated.len(), DURESS_OFFSET + DURESS_TABLE_LEN);
+        assert_eq!(migrated[..SALT_LEN], head[..SALT_LEN]);
+        assert!(duress_target(&first, "value:1234").unwrap().mode == DuressMode::StayOnCalculator);
+        clear_duress_code_in(&first, &vault, &key).unwrap();
+        assert!(duress_target(&first, "value:1234").is_none());
+
+        // الصيغة الثانية: مُحقِّق رمز الفتح يبقى، وضبط رمز جديد يزيل القديم
+        let second = dir.join("second");
+        fs::write(&second, layout(&head, &sealed("value:777"))).unwrap();
+        assert!(matches!(match_code_in(&second, &["value:49"]), CodeMatch::Unlock(_)));
+        assert!(duress_target(&second, "value:777").is_some());
+        set_duress_code_in(&second, "value:888", &vault, &key, DuressMode::ShowUnlock).unwrap();
+        assert!(duress_target(&second, "value:777").is_none());
+        assert!(duress_target(&second, "value:888").is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn destroy_erases_keys_but_keeps_header_shape() {
+        let dir = temp_dir("destroy");
+        VaultHeader::create(&dir, "correct horse", CipherKind::default()).unwrap();
+        assert!(open_vault(&dir, &Credential::Secret("correct horse")).is_ok());
+
+        // ملف بالصيغة القديمة بلا ترويسة، وحاوية بالصيغة الجديدة
+        let legacy = dir.join("legacy.secure");
+        fs::write(&legacy, [7u8; 64]).unwrap();
+        let container = dir.join("container.secure");
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&[0u8; 60]);
+        fs::write(&container, &data).unwrap();
+
+        let before = VaultHeader::load(&dir).unwrap();
+        destroy_vault(&dir).unwrap();
+        let after = VaultHeader::load(&dir).unwrap();
+
+        assert!(open_vault(&dir, &Credential::Secret("correct horse")).is_err());
+        assert_eq!(after.slots.len(), before.slots.len());
+        for (old, new) in before.slots.iter().zip(&after.slots) {
+            assert!(new.kind == old.kind && new.kind == SlotKind::Password);
+            assert_ne!(new.salt, old.salt);
+            assert_ne!(new.wrapped_key, old.wrapped_key);
+            assert_eq!(new.wrapped_key.len(), old.wrapped_key.len());
+        }
+        assert!(!legacy.exists());
+        assert!(container.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::atomic::write_atomic;
+use super::cipher::CipherKind;
+use super::padding::PaddingPolicy;
+use super::keys::generate_key;
+use super::secret::SecretKey;
+use super::shred::DEFAULT_SHRED_PASSES;
+use super::slots::{Credential, KeySlot, SlotKind};
+use super::totp::TotpConfig;
+
+const HEADER_FILE: &str = "vault.json";
+const HEADER_VERSION: u32 = 2;
+
+// دقائق الخمول قبل القفل التلقائي للخزنات الجديدة والقديمة التي لم تضبطه
+pub const DEFAULT_AUTO_LOCK_MINUTES: u32 = 5;
+
+// ترويسة الخزنة: فتحات المفاتيح (المفتاح الرئيسي مغلفاً بكل بيانات اعتماد على حدة) والإعدادات العامة
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VaultHeader {
+    pub version: u32,
+    #[serde(default)]
+    pub slots: Vec<KeySlot>,
+    // خوارزمية تشفير الملفات الجديدة - الملفات الموجودة تحمل خوارزميتها في ترويستها
+    #[serde(default)]
+    pub cipher: CipherKind,
+    // حشو الملفات الجديدة لإخفاء أحجامها - يمكن تغييره في أي وقت لأن كل ملف يسجل حالته
+    #[serde(default)]
+    pub padding: PaddingPolicy,
+    // ضغط الملفات الجديدة بـ zstd قبل التشفير - الصيغ المضغوطة أصلاً تُتخطى تلقائياً
+    #[serde(default)]
+    pub compression: bool,
+    // مرات الكتابة فوق الملفات المحذوفة (الأصول بعد تشفيرها والحاويات المحذوفة) - صفر للحذف العادي
+    #[serde(default = "default_shred_passes")]
+    pub shred_passes: u32,
+    // إتلاف مفاتيح الخزنة بعد هذا العدد من محاولات الفتح الفاشلة المتتالية - اختياري
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wipe_after: Option<u32>,
+    // القفل والعودة إلى الحاسبة بعد هذه الدقائق بلا نشاط - صفر لتعطيله
+    #[serde(default = "default_auto_lock_minutes")]
+    pub auto_lock_minutes: u32,
+    // القفل عند مغادرة النافذة أو تصغيرها
+    #[serde(default)]
+    pub lock_on_blur: bool,
+    // رمز التحقق الزمني المطلوب قبل شاشة كلمة المرور - اختياري
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<TotpConfig>,
+}
+
+impl VaultHeader {
+    pub(super) fn path(vault_dir: &Path) -> PathBuf {
+        vault_dir.join(HEADER_FILE)
+    }
+
+    pub fn load(vault_dir: &Path) -> Option<Self> {
+        let data = fs::read_to_string(Self::path(vault_dir)).ok()?;
+        let mut header: Self = serde_json::from_str(&data).ok()?;
+        // الإصدار 1 يحمل فتحة كلمة مرور واحدة بحقولها في جذر الترويسة
+        if header.version < 2 {
+            header.slots = vec![serde_json::from_str(&data).ok()?];
+            header.version = HEADER_VERSION;
+        }
+        Some(header)
+    }
+
+    pub fn save(&self, vault_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(vault_dir)?;
+        write_atomic(&Self::path(vault_dir), &self.encode()?)?;
+        Ok(())
+    }
+
+    pub(super) fn encode(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(serde_json::to_string_pretty(self)?.into_bytes())
+    }
+
+    // إنشاء خزنة جديدة بكلمة مرور - يعيد المفتاح الرئيسي ليبقى في الذاكرة فقط
+    pub fn create(vault_dir: &Path, password: &str, cipher: CipherKind) -> Result<SecretKey, Box<dyn std::error::Error>> {
+        let master_key = generate_key();
+        let slot = KeySlot::seal(SlotKind::Password, "", None, &Credential::Secret(password), &master_key, None)?;
+        Self::with_slot(slot, cipher).save(vault_dir)?;
+        Ok(master_key)
+    }
+
+    // ترويسة خزنة وهمية بإعدادات خزنة جديدة (انظر decoy.rs)
+    pub(super) fn decoy(cipher: CipherKind) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_slot(KeySlot::decoy()?, cipher))
+    }
+
+    fn with_slot(slot: KeySlot, cipher: CipherKind) -> Self {
+        VaultHeader {
+            version: HEADER_VERSION,
+            slots: vec![slot],
+            cipher,
+            padding: PaddingPolicy::default(),
+            compression: false,
+            shred_passes: DEFAULT_SHRED_PASSES,
+            wipe_after: None,
+            auto_lock_minutes: DEFAULT_AUTO_LOCK_MINUTES,
+            lock_on_blur: false,
+            totp: None,
+        }
+    }
+
+    // تجربة الفتحات بالترتيب - يعيد رقم الفتحة التي فُتحت ومفتاح تغليفها والمفتاح الرئيسي.
+    // فتحة يتعذر تجربتها (ملف مفتاح غير موصول مثلاً) لا توقف البقية، لكن خطأها أوضح من
+    // "كلمة المرور غير صحيحة" إن لم تفتح أي فتحة
+    pub fn unlock(&self, credential: &Credential) -> Result<(usize, SecretKey, SecretKey), Box<dyn std::error::Error>> {
+        let mut slot_error = None;
+        for (index, slot) in self.slots.iter().enumerate() {
+            let kek = match slot.derive_kek(credential) {
+                Ok(Some(kek)) => kek,
+                Ok(None) => continue,
+                Err(e) => {
+                    slot_error.get_or_insert(e);
+                    continue;
+                }
+            };
+            if let Ok(master_key) = slot.unwrap_master(&kek) {
+                return Ok((index, kek, master_key));
+            }
+        }
+        if let Some(e) = slot_error {
+            return Err(e);
+        }
+        Err(match credential {
+            Credential::Secret(_) => "كلمة المرور غير صحيحة".into(),
+            Credential::Keyfile(_) => "ملف المفتاح غير صحيح".into(),
+        })
+    }
+}
+
+fn default_shred_passes() -> u32 {
+    DEFAULT_SHRED_PASSES
+}
+
+fn default_auto_lock_minutes() -> u32 {
+    DEFAULT_AUTO_LOCK_MINUTES
+}
@@ -0,0 +1,511 @@
+use aes_gcm::aead::OsRng;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::atomic::write_atomic;
+use super::cipher::CipherKind;
+use super::codes::constant_time_eq;
+use super::header::VaultHeader;
+use super::keys::derive_subkey;
+use super::secret::SecretKey;
+use super::shred::{secure_delete, DEFAULT_SHRED_PASSES};
+
+// الفهرس مشفر وموثّق (وسم AEAD هو الـ MAC) بمفتاح فرعي من المفتاح الرئيسي:
+//   magic "CVIX" | version u8 | cipher u8 | generation u64 | nonce || ciphertext(JSON)
+// وكل ما قبل الـ nonce بيانات مرفقة. رقم الجيل يزيد مع كل حفظ، ونسخة منه موقّعة في مجلد
+// الإعدادات تكشف استرجاع فهرس أقدم. الإصدار 1 بلا رقم جيل (يُعامل كصفر).
+// الفهرس النصي القديم يُقرأ مرة واحدة ثم يُحذف عند أول حفظ
+const INDEX_FILE: &str = "index.vault";
+const LEGACY_INDEX_FILE: &str = "index.json";
+const INDEX_MAGIC: &[u8; 4] = b"CVIX";
+const INDEX_VERSION: u8 = 2;
+
+// المفتاح الذي شُفّر به الملف - الإدخالات القديمة في الفهرس لا تحمل هذا الحقل
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum KeyScheme {
+    #[default]
+    Legacy,
+    Envelope,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EncryptedFile {
+    // معرّف ثابت يُوثّق داخل الملف المشفر - فارغ في إدخالات الصيغة القديمة
+    #[serde(default)]
+    pub file_id: String,
+    pub name: String,
+    pub original_extension: String,
+    pub encrypted_path: String,
+    pub size: u64,
+    pub created_at: String,
+    #[serde(default)]
+    pub scheme: KeyScheme,
+    // مفتاح بيانات الملف مغلفاً بالمفتاح الرئيسي للخزنة
+    #[serde(default)]
+    pub wrapped_key: String,
+    // SHA-256 للنص الأصلي لفحص السلامة - فارغ في الإدخالات الأقدم حتى يملأه أول فحص
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sha256: String,
+    // مختصر المحتوى بمفتاح الخزنة لكشف الملفات المكررة عند الاستيراد (انظر content_mac)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub content_mac: String,
+    // إدخال مرتبط بحاوية إدخال آخر: الاسم المسجل داخل تلك الحاوية إن اختلف عن اسم هذا الإدخال.
+    // الحاوية المشتركة تُحذف مع آخر إدخال يشير إليها
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_name: Option<String>,
+    // سبب آخر فشل في ترحيل إدخال بالصيغة القديمة - يبقى في الفهرس المشفر حتى ينجح الترحيل
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migration_error: Option<String>,
+}
+
+fn index_key(master_key: &[u8; 32]) -> SecretKey {
+    derive_subkey(master_key, b"calculator-vault/index")
+}
+
+// HMAC لمختصر النص الأصلي بمفتاح فرعي من المفتاح الرئيسي: تطابقه يعني تطابق المحتوى،
+// ولا يفيد من لا يملك المفتاح. يُحسب من المختصر المحفوظ حتى يُعاد حسابه عند تدوير المفتاح
+// دون فك أي ملف
+pub fn content_mac(master_key: &[u8; 32], sha256: &str) -> String {
+    let key = derive_subkey(master_key, b"calculator-vault/dedup");
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&*key).expect("HMAC يقبل أي طول مفتاح");
+    mac.update(sha256.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// قبل الفتح لا يمكن قراءة الفهرس أصلاً - لا أسماء ولا أحجام ولا تواريخ
+pub fn load_index(vault_dir: &Path, master_key: &[u8; 32]) -> Result<Vec<EncryptedFile>, Box<dyn std::error::Error>> {
+    let data = match fs::read(vault_dir.join(INDEX_FILE)) {
+        Ok(data) => data,
+        Err(_) => {
+            // فهرس نصي قديم: يُنقل فوراً إلى الصيغة المشفرة
+            let Some(files) = fs::read_to_string(vault_dir.join(LEGACY_INDEX_FILE))
+                .ok()
+                .and_then(|json| serde_json::from_str::<Vec<EncryptedFile>>(&json).ok())
+            else {
+                return Ok(Vec::new());
+            };
+            save_index(vault_dir, master_key, &files)?;
+            return Ok(files);
+        }
+    };
+
+    let (cipher, _, header_len) = parse_header(&data)?;
+    let (aad, sealed) = data.split_at(header_len);
+    let json = cipher.backend().open(&index_key(master_key), aad, sealed)
+        .map_err(|_| "تعذر فك تشفير فهرس الخزنة - ربما عُدّل خارج التطبيق")?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+// الخوارزمية ورقم الجيل وطول الترويسة - دون فك التشفير
+fn parse_header(data: &[u8]) -> Result<(CipherKind, u64, usize), Box<dyn std::error::Error>> {
+    if data.len() < 6 || &data[..4] != INDEX_MAGIC {
+        return Err("فهرس الخزنة تالف".into());
+    }
+    let cipher = CipherKind::from_id(data[5])?;
+    match data[4] {
+        1 => Ok((cipher, 0, 6)),
+        2 if data.len() >= 14 => {
+            let generation = u64::from_be_bytes(data[6..14].try_into()?);
+            Ok((cipher, generation, 14))
+        }
+        _ => Err("فهرس الخزنة تالف".into()),
+    }
+}
+
+fn index_generation(vault_dir: &Path) -> u64 {
+    fs::read(vault_dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|data| parse_header(&data).ok())
+        .map_or(0, |(_, generation, _)| generation)
+}
+
+pub fn save_index(vault_dir: &Path, master_key: &[u8; 32], files: &[EncryptedFile]) -> Result<(), Box<dyn std::error::Error>> {
+    save_index_in(counter_table_path().as_deref(), vault_dir, master_key, files)
+}
+
+fn save_index_in(
+    counters: Option<&Path>,
+    vault_dir: &Path,
+    master_key: &[u8; 32],
+    files: &[EncryptedFile],
+) -> Result<(), Box<dyn std::error::Error>> {
+    // الجيل الجديد يتجاوز الفهرس الحالي والنسخة المحفوظة معاً، فلا يعود العداد للخلف أبداً
+    let stored = counters.and_then(|table| read_counter(table, vault_dir, master_key).ok().flatten()).unwrap_or(0);
+    let generation = index_generation(vault_dir).max(stored) + 1;
+    write_index(vault_dir, master_key, files, generation)?;
+
+    // النسخة تُكتب بعد الفهرس: انقطاع بينهما يترك الفهرس أحدث من النسخة، وهذا ليس استرجاعاً.
+    // تعذر كتابتها (مجلد إعدادات للقراءة فقط) لا يمنع الحفظ - يفقد الكشف فقط
+    if let Some(table) = counters {
+        let _ = write_counter(table, vault_dir, master_key, generation);
+    }
+    // الفهرس النصي يحمل أسماء الملفات مكشوفة - يُحذف حذفاً آمناً كالملفات الأصلية
+    let legacy = vault_dir.join(LEGACY_INDEX_FILE);
+    if legacy.exists() {
+        let passes = VaultHeader::load(vault_dir).map_or(DEFAULT_SHRED_PASSES, |h| h.shred_passes);
+        let _ = secure_delete(&legacy, passes);
+    }
+    Ok(())
+}
+
+// كتابة الفهرس بجيل معطى دون المرور بسجل الإصدارات - تستخدمها الخزنات الوهمية أيضاً
+pub(super) fn write_index(
+    vault_dir: &Path,
+    master_key: &[u8; 32],
+    files: &[EncryptedFile],
+    generation: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(vault_dir)?;
+    let cipher = VaultHeader::load(vault_dir).map(|h| h.cipher).unwrap_or_default();
+    let mut data = INDEX_MAGIC.to_vec();
+    data.push(INDEX_VERSION);
+    data.push(cipher.id());
+    data.extend_from_slice(&generation.to_be_bytes());
+    let sealed = cipher.backend().seal(&index_key(master_key), &data, &serde_json::to_vec(files)?)?;
+    data.extend_from_slice(&sealed);
+    write_atomic(&vault_dir.join(INDEX_FILE), &data)?;
+    Ok(())
+}
+
+// نتيجة مقارنة جيل الفهرس على القرص بالنسخة المحفوظة في مجلد الإعدادات
+pub enum IndexFreshness {
+    Current,
+    // الفهرس أقدم من آخر ما حفظه التطبيق: نسخة قديمة استُرجعت (مزامنة أو مهاجم)
+    RolledBack { found: u64, expected: u64 },
+    // النسخة موجودة لكن توقيعها لا يطابق - عُدّلت خارج التطبيق
+    CounterTampered,
+    // فهرس له أجيال سابقة بلا نسخة - مجلد الإعدادات حُذف أو نُقلت الخزنة من جهاز آخر
+    CounterMissing,
+}
+
+impl IndexFreshness {
+    pub fn warning(&self) -> Option<String> {
+        match self {
+            IndexFreshness::Current => None,
+            IndexFreshness::RolledBack { found, expected } => Some(format!(
+                "⚠️ فهرس الخزنة أقدم من آخر نسخة حفظها التطبيق (الجيل {} بدل {}) - ربما استُرجعت نسخة قديمة أو عُدّل خارج التطبيق",
+                found, expected
+            )),
+            IndexFreshness::CounterTampered => Some("⚠️ سجل إصدارات الفهرس عُدّل خارج التطبيق".to_string()),
+            IndexFreshness::CounterMissing => Some("⚠️ لا يوجد سجل لإصدارات الفهرس على هذا الجهاز - تعذر التحقق من أنه أحدث نسخة".to_string()),
+        }
+    }
+}
+
+pub fn check_index_freshness(vault_dir: &Path, master_key: &[u8; 32]) -> IndexFreshness {
+    match counter_table_path() {
+        Some(table) => freshness_in(&table, vault_dir, master_key),
+        None => IndexFreshness::CounterMissing,
+    }
+}
+
+fn freshness_in(table: &Path, vault_dir: &Path, master_key: &[u8; 32]) -> IndexFreshness {
+    let found = index_generation(vault_dir);
+    match read_counter(table, vault_dir, master_key) {
+        Ok(Some(expected)) if found < expected => IndexFreshness::RolledBack { found, expected },
+        Ok(Some(_)) => IndexFreshness::Current,
+        Ok(None) => {
+            // أول فتح على هذا الجهاز أو بعد حذف السجل: نبدأ السجل من الجيل الحالي
+            let _ = write_counter(table, vault_dir, master_key, found);
+            if found > 1 { IndexFreshness::CounterMissing } else { IndexFreshness::Current }
+        }
+        Err(_) => IndexFreshness::CounterTampered,
+    }
+}
+
+// نسخ أرقام الجيل لكل الخزنات في ملف واحد بحجم ثابت، يُنشأ ببايتات عشوائية:
+//   وسم 16 | nonce 16 | generation u64 XOR قناع | HMAC-SHA256(الوسم || nonce || generation)
+// الوسم والقناع والتوقيع بمفاتيح فرعية من المفتاح الرئيسي، فلا يميز سجلاً من البايتات العشوائية
+// حوله إلا صاحب الخزنة: الملف لا يكشف عدد الخزنات ولا أي مجلد له سجل. لا علامة للموضع الفارغ،
+// فسجل جديد يأخذ موضعاً عشوائياً وقد يقع على سجل خزنة أخرى - نادر بهذا العدد من المواضع، وتلك
+// الخزنة تُنبَّه بأنه لا يوجد سجل ثم يبدأ سجلها من جديد. سجل بوسم هذه الخزنة لا يتحقق توقيعه
+// يعني تعديلاً خارج التطبيق. عند تدوير المفتاح الرئيسي يُنقل السجل إلى المفتاح الجديد في موضعه.
+// الصيغة الأقدم (64 موضعاً بوسم هو مختصر المسار وأصفار للفارغ) تُقرأ: مواضعها الفارغة تصبح
+// عشوائية، وسجل كل خزنة يُستبدل بسجل موسوم بمفتاحها عند أول حفظ لها
+const COUNTER_FILE: &str = "state";
+const COUNTER_SLOTS: usize = 256;
+const LEGACY_COUNTER_SLOTS: usize = 64;
+const COUNTER_TAG: usize = 16;
+const COUNTER_RECORD: usize = COUNTER_TAG + 16 + 8 + 32;
+const COUNTER_TABLE_LEN: usize = COUNTER_SLOTS * COUNTER_RECORD;
+const LEGACY_COUNTER_TABLE_LEN: usize = LEGACY_COUNTER_SLOTS * COUNTER_RECORD;
+
+fn counter_table_path() -> Option<PathBuf> {
+    // الاختبارات لا تكتب في سجل المستخدم - كل اختبار يمرر جدوله إلى save_index_in
+    if cfg!(test) {
+        return None;
+    }
+    Some(dirs::config_dir()?.join("calculator").join(COUNTER_FILE))
+}
+
+fn canonical_path(vault_dir: &Path) -> String {
+    let canonical = fs::canonicalize(vault_dir).unwrap_or_else(|_| vault_dir.to_path_buf());
+    canonical.to_string_lossy().into_owned()
+}
+
+// وسم سجل الخزنة: مسار جذرها بمفتاح فرعي من مفتاحها الرئيسي
+fn counter_tag(vault_dir: &Path, master_key: &[u8; 32]) -> [u8; COUNTER_TAG] {
+    let key = derive_subkey(master_key, b"calculator-vault/generation-tag");
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&*key).expect("HMAC يقبل أي طول مفتاح");
+    mac.update(canonical_path(vault_dir).as_bytes());
+    mac.finalize().into_bytes()[..COUNTER_TAG].try_into().expect("HMAC-SHA256 أطول من الوسم")
+}
+
+// وسم الصيغة الأقدم: مختصر المسار وحده
+fn legacy_counter_tag(vault_dir: &Path) -> [u8; COUNTER_TAG] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"calculator-vault/generation");
+    hasher.update(canonical_path(vault_dir).as_bytes());
+    hasher.finalize()[..COUNTER_TAG].try_into().expect("SHA-256 أطول من الوسم")
+}
+
+fn counter_mac(master_key: &[u8; 32], tag: &[u8], nonce: &[u8], generation: u64) -> [u8; 32] {
+    let key = derive_subkey(master_key, b"calculator-vault/generation");
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&*key).expect("HMAC يقبل أي طول مفتاح");
+    mac.update(tag);
+    mac.update(nonce);
+    mac.update(&generation.to_be_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+fn counter_mask(master_key: &[u8; 32], nonce: &[u8]) -> u64 {
+    let key = derive_subkey(master_key, b"calculator-vault/generation-mask");
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&*key).expect("HMAC يقبل أي طول مفتاح");
+    mac.update(nonce);
+    u64::from_be_bytes(mac.finalize().into_bytes()[..8].try_into().expect("8 بايت"))
+}
+
+fn random_counter_table() -> Vec<u8> {
+    let mut table = vec![0u8; COUNTER_TABLE_LEN];
+    OsRng.fill_bytes(&mut table);
+    table
+}
+
+fn load_counter_table(path: &Path) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    match fs::read(path) {
+        Ok(table) if table.len() == COUNTER_TABLE_LEN => Ok(Some(table)),
+        Ok(legacy) if legacy.len() == LEGACY_COUNTER_TABLE_LEN => {
+            let mut table = random_counter_table();
+            for (slot, record) in legacy.chunks(COUNTER_RECORD).enumerate() {
+                if record[..COUNTER_TAG].iter().any(|&b| b != 0) {
+                    table[slot * COUNTER_RECORD..(slot + 1) * COUNTER_RECORD].copy_from_slice(record);
+                }
+            }
+            Ok(Some(table))
+        }
+        Ok(_) => Err("سجل إصدارات الفهرس تالف".into()),
+        Err(_) => Ok(None),
+    }
+}
+
+fn find_record(table: &[u8], tag: &[u8]) -> Option<usize> {
+    (0..COUNTER_SLOTS)
+        .map(|slot| slot * COUNTER_RECORD)
+        .find(|&offset| constant_time_eq(&table[offset..offset + COUNTER_TAG], tag))
+}
+
+// موضع سجل الخزنة ووسمه - بوسمها الحالي أو بوسم الصيغة الأقدم
+fn locate_record(table: &[u8], vault_dir: &Path, master_key: &[u8; 32]) -> Option<(usize, [u8; COUNTER_TAG])> {
+    [counter_tag(vault_dir, master_key), legacy_counter_tag(vault_dir)]
+        .into_iter()
+        .find_map(|tag| find_record(table, &tag).map(|offset| (offset, tag)))
+}
+
+fn open_counter(table: &[u8], offset: usize, tag: &[u8], master_key: &[u8; 32]) -> Result<u64, Box<dyn std::error::Error>> {
+    let record = &table[offset + COUNTER_TAG..offset + COUNTER_RECORD];
+    let (nonce, rest) = record.split_at(16);
+    let generation = u64::from_be_bytes(rest[..8].try_into()?) ^ counter_mask(master_key, nonce);
+    if !constant_time_eq(&counter_mac(master_key, tag, nonce, generation), &rest[8..]) {
+        return Err("سجل الجيل تالف".into());
+    }
+    Ok(generation)
+}
+
+fn seal_counter(table: &mut [u8], offset: usize, vault_dir: &Path, master_key: &[u8; 32], generation: u64) {
+    let tag = counter_tag(vault_dir, master_key);
+    let mut nonce = [0u8; 16];
+    OsRng.fill_bytes(&mut nonce);
+    let record = &mut table[offset..offset + COUNTER_RECORD];
+    record[..COUNTER_TAG].copy_from_slice(&tag);
+    record[COUNTER_TAG..COUNTER_TAG + 16].copy_from_slice(&nonce);
+    record[COUNTER_TAG + 16..COUNTER_TAG + 24].copy_from_slice(&(generation ^ counter_mask(master_key, &nonce)).to_be_bytes());
+    record[COUNTER_TAG + 24..].copy_from_slice(&counter_mac(master_key, &tag, &nonce, generation));
+}
+
+fn read_counter(path: &Path, vault_dir: &Path, master_key: &[u8; 32]) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    let Some(table) = load_counter_table(path)? else { return Ok(None) };
+    let Some((offset, tag)) = locate_record(&table, vault_dir, master_key) else { return Ok(None) };
+    open_counter(&table, offset, &tag, master_key).map(Some)
+}
+
+fn write_counter(path: &Path, vault_dir: &Path, master_key: &[u8; 32], generation: u64) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // جدول تالف يُستبدل: تفقد الخزنات سجلاتها وتُنبَّه بأنه لا يوجد سجل، ولا يُكتب فوقه جزئياً
+    let mut table = load_counter_table(path).ok().flatten().unwrap_or_else(random_counter_table);
+    let offset = locate_record(&table, vault_dir, master_key)
+        .map(|(offset, _)| offset)
+        .unwrap_or_else(|| (OsRng.next_u32() as usize % COUNTER_SLOTS) * COUNTER_RECORD);
+    seal_counter(&mut table, offset, vault_dir, master_key, generation);
+    write_atomic(path, &table)?;
+    Ok(())
+}
+
+// تدوير المفتاح الرئيسي: السجل يُختم بالمفتاح الجديد في موضعه فلا يضيع رقم الجيل ولا يبقى
+// سجل يتيم. سجل نُقل قبل انقطاع سابق لا يُوجد بالمفتاح القديم فلا يُمس
+pub(super) fn rekey_counter(vault_dir: &Path, old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<(), Box<dyn std::error::Error>> {
+    match counter_table_path() {
+        Some(table) => rekey_counter_in(&table, vault_dir, old_key, new_key),
+        None => Ok(()),
+    }
+}
+
+fn rekey_counter_in(path: &Path, vault_dir: &Path, old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(mut table) = load_counter_table(path)? else { return Ok(()) };
+    let Some((offset, tag)) = locate_record(&table, vault_dir, old_key) else { return Ok(()) };
+    let generation = open_counter(&table, offset, &tag, old_key)?;
+    seal_counter(&mut table, offset, vault_dir, new_key, generation);
+    write_atomic(path, &table)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::temp_dir;
+
+    const KEY: [u8; 32] = [1u8; 32];
+
+    #[test]
+    fn rollback_is_detected() {
+        let dir = temp_dir("rollback");
+        let (vault, counters) = (dir.join("vault"), dir.join("state"));
+        save_index_in(Some(&counters), &vault, &KEY, &[]).unwrap();
+        assert!(matches!(freshness_in(&counters, &vault, &KEY), IndexFreshness::Current));
+
+        let old = fs::read(vault.join(INDEX_FILE)).unwrap();
+        save_index_in(Some(&counters), &vault, &KEY, &[]).unwrap();
+        fs::write(vault.join(INDEX_FILE), &old).unwrap();
+        assert!(matches!(
+            freshness_in(&counters, &vault, &KEY),
+            IndexFreshness::RolledBack { found: 1, expected: 2 }
+        ));
+
+        // الحفظ بعد الاسترجاع يتجاوز النسخة المحفوظة لا الفهرس المسترجع
+        save_index_in(Some(&counters), &vault, &KEY, &[]).unwrap();
+        assert_eq!(index_generation(&vault), 3);
+        assert!(matches!(freshness_in(&counters, &vault, &KEY), IndexFreshness::Current));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_and_tampered_counters() {
+        let dir = temp_dir("counter");
+        let (vault, counters) = (dir.join("vault"), dir.join("state"));
+        save_index_in(Some(&counters), &vault, &KEY, &[]).unwrap();
+        save_index_in(Some(&counters), &vault, &KEY, &[]).unwrap();
+
+        // بلا سجل: تنبيه مرة واحدة ثم يبدأ السجل من الجيل الحالي
+        fs::remove_file(&counters).unwrap();
+        assert!(matches!(freshness_in(&counters, &vault, &KEY), IndexFreshness::CounterMissing));
+        assert!(matches!(freshness_in(&counters, &vault, &KEY), IndexFreshness::Current));
+
+        let table = fs::read(&counters).unwrap();
+        let offset = find_record(&table, &counter_tag(&vault, &KEY)).unwrap();
+        let mut tampered = table.clone();
+        tampered[offset + COUNTER_TAG + 16] ^= 1;
+        fs::write(&counters, &tampered).unwrap();
+        assert!(matches!(freshness_in(&counters, &vault, &KEY), IndexFreshness::CounterTampered));
+
+        // بمفتاح آخر لا يُعرف السجل أصلاً
+        fs::write(&counters, &table).unwrap();
+        assert!(matches!(freshness_in(&counters, &vault, &[2u8; 32]), IndexFreshness::CounterMissing));
+
+        fs::write(&counters, &table[..COUNTER_TABLE_LEN - 1]).unwrap();
+        assert!(matches!(freshness_in(&counters, &vault, &KEY), IndexFreshness::CounterTampered));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn vaults_keep_separate_counters() {
+        let dir = temp_dir("vaults");
+        let (first, second, counters) = (dir.join("first"), dir.join("second"), dir.join("state"));
+        for _ in 0..3 {
+            save_index_in(Some(&counters), &first, &KEY, &[]).unwrap();
+        }
+        // الموضع العشوائي قد يقع على سجل الأولى - يُعاد حتى يُختبر التجاور لا التصادم
+        let table = fs::read(&counters).unwrap();
+        while read_counter(&counters, &first, &KEY).unwrap().is_none() || read_counter(&counters, &second, &[2u8; 32]).unwrap().is_none() {
+            fs::write(&counters, &table).unwrap();
+            write_counter(&counters, &second, &[2u8; 32], 1).unwrap();
+        }
+        assert_eq!(read_counter(&counters, &first, &KEY).unwrap(), Some(3));
+        assert_eq!(read_counter(&counters, &second, &[2u8; 32]).unwrap(), Some(1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_index_is_encrypted_and_shredded() {
+        let dir = temp_dir("legacy-index");
+        fs::write(dir.join(LEGACY_INDEX_FILE), "[]").unwrap();
+        assert!(load_index(&dir, &KEY).unwrap().is_empty());
+        assert!(!dir.join(LEGACY_INDEX_FILE).exists());
+        // لا يبقى في المجلد إلا الفهرس المشفر - ولا الاسم العشوائي الذي يمر به الحذف الآمن
+        let names: Vec<_> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(names, [INDEX_FILE]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotation_moves_the_counter() {
+        let dir = temp_dir("rotation");
+        let (vault, counters) = (dir.join("vault"), dir.join("state"));
+        for _ in 0..3 {
+            save_index_in(Some(&counters), &vault, &KEY, &[]).unwrap();
+        }
+        let before = fs::read(&counters).unwrap();
+        let offset = find_record(&before, &counter_tag(&vault, &KEY)).unwrap();
+
+        rekey_counter_in(&counters, &vault, &KEY, &[2u8; 32]).unwrap();
+        assert_eq!(read_counter(&counters, &vault, &[2u8; 32]).unwrap(), Some(3));
+        assert_eq!(read_counter(&counters, &vault, &KEY).unwrap(), None);
+        let after = fs::read(&counters).unwrap();
+        assert_eq!(find_record(&after, &counter_tag(&vault, &[2u8; 32])), Some(offset));
+        // تدوير انقطع بعد نقل السجل يُكمل دون أن يمسه
+        rekey_counter_in(&counters, &vault, &KEY, &[2u8; 32]).unwrap();
+        assert_eq!(fs::read(&counters).unwrap(), after);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_table_loses_its_markers() {
+        let dir = temp_dir("legacy-counter");
+        let (vault, counters) = (dir.join("vault"), dir.join("state"));
+        fs::create_dir_all(&vault).unwrap();
+        // الصيغة الأقدم: وسم مختصر المسار في الموضع 5 وأصفار في بقية المواضع
+        let mut legacy = vec![0u8; LEGACY_COUNTER_TABLE_LEN];
+        let tag = legacy_counter_tag(&vault);
+        let offset = 5 * COUNTER_RECORD;
+        let nonce = [9u8; 16];
+        legacy[offset..offset + COUNTER_TAG].copy_from_slice(&tag);
+        legacy[offset + COUNTER_TAG..offset + COUNTER_TAG + 16].copy_from_slice(&nonce);
+        legacy[offset + COUNTER_TAG + 16..offset + COUNTER_TAG + 24].copy_from_slice(&(4 ^ counter_mask(&KEY, &nonce)).to_be_bytes());
+        legacy[offset + COUNTER_TAG + 24..offset + COUNTER_RECORD].copy_from_slice(&counter_mac(&KEY, &tag, &nonce, 4));
+        fs::write(&counters, &legacy).unwrap();
+        assert_eq!(read_counter(&counters, &vault, &KEY).unwrap(), Some(4));
+
+        write_counter(&counters, &vault, &KEY, 5).unwrap();
+        let table = fs::read(&counters).unwrap();
+        assert_eq!(table.len(), COUNTER_TABLE_LEN);
+        assert_eq!(find_record(&table, &counter_tag(&vault, &KEY)), Some(offset));
+        assert_eq!(find_record(&table, &tag), None);
+        assert_eq!(find_record(&table, &[0u8; COUNTER_TAG]), None);
+        assert_eq!(read_counter(&counters, &vault, &KEY).unwrap(), Some(5));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
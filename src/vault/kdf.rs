@@ -0,0 +1,41 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::secret::SecretKey;
+
+// معاملات Argon2id - تحفظ في ترويسة الخزنة حتى يمكن رفعها لاحقاً دون كسر الخزنات القديمة
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub m_cost: u32, // الذاكرة بالكيلوبايت
+    pub t_cost: u32, // عدد التمريرات
+    pub p_cost: u32, // درجة التوازي
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+        }
+    }
+}
+
+pub fn derive_key(password: &[u8], salt: &[u8], params: &KdfParams) -> Result<SecretKey, Box<dyn std::error::Error>> {
+    let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| format!("معاملات اشتقاق غير صالحة: {}", e))?;
+    let argon = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+    let mut key = SecretKey::zeroed();
+    argon.hash_password_into(password, salt, &mut *key)
+        .map_err(|e| format!("خطأ في اشتقاق المفتاح: {}", e))?;
+    Ok(key)
+}
+
+// المفتاح الثابت القديم - يبقى فقط لقراءة الملفات المشفرة قبل اعتماد كلمة المرور
+pub fn legacy_key() -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"49_secret_calculator_key_2024_ultra_secure");
+    hasher.finalize().into()
+}
@@ -0,0 +1,52 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use super::secret::SecretKey;
+
+pub fn generate_key() -> SecretKey {
+    let mut key = SecretKey::zeroed();
+    OsRng.fill_bytes(&mut *key);
+    key
+}
+
+// مفتاح فرعي مستقل لكل استخدام (الفهرس مثلاً) حتى لا يُستخدم المفتاح الرئيسي مباشرة في غير التغليف
+pub fn derive_subkey(master_key: &[u8; 32], label: &[u8]) -> SecretKey {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(master_key).expect("HMAC يقبل أي طول مفتاح");
+    mac.update(label);
+    let mut key = SecretKey::zeroed();
+    key.copy_from_slice(&mac.finalize().into_bytes());
+    key
+}
+
+// تغليف مفتاح بمفتاح آخر (nonce || ciphertext) بصيغة hex لتخزينه في الترويسة أو الفهرس
+pub fn wrap_key(kek: &[u8; 32], key: &[u8; 32]) -> Result<String, Box<dyn std::error::Error>> {
+    let cipher = Aes256Gcm::new_from_slice(kek)?;
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let sealed = cipher.encrypt(&Nonce::from(nonce_bytes), key.as_slice())
+        .map_err(|e| format!("خطأ في تغليف المفتاح: {:?}", e))?;
+
+    let mut out = nonce_bytes.to_vec();
+    out.extend_from_slice(&sealed);
+    Ok(hex::encode(out))
+}
+
+pub fn unwrap_key(kek: &[u8; 32], wrapped: &str) -> Result<SecretKey, Box<dyn std::error::Error>> {
+    let data = hex::decode(wrapped)?;
+    if data.len() < 12 {
+        return Err("مفتاح مغلف غير صالح".into());
+    }
+    let (nonce_bytes, sealed) = data.split_at(12);
+    let nonce = Nonce::from(<[u8; 12]>::try_from(nonce_bytes)?);
+
+    let cipher = Aes256Gcm::new_from_slice(kek)?;
+    let key = Zeroizing::new(cipher.decrypt(&nonce, sealed)
+        .map_err(|_| "تعذر فك تغليف المفتاح")?);
+    SecretKey::from_slice(&key)
+}
@@ -0,0 +1,72 @@
+//! منطق الخزنة المستقل عن الواجهة: اشتقاق المفتاح من كلمة المرور وترويسة الخزنة المخزنة على القرص.
+//!
+//! كل ملف يشفر بمفتاح بيانات عشوائي خاص به، ومفتاح البيانات يغلف بالمفتاح الرئيسي للخزنة،
+//! والمفتاح الرئيسي بدوره يغلف في فتحة مستقلة لكل بيانات اعتماد (كلمة مرور، مفتاح استرداد، ملف مفتاح).
+
+mod atomic;
+
+mod secret;
+pub use secret::{suppress_core_dumps, CoreDumpGuard, SecretKey};
+
+mod kdf;
+pub use kdf::legacy_key;
+
+mod keys;
+pub use keys::{generate_key, unwrap_key, wrap_key};
+
+mod slots;
+pub use slots::{generate_recovery_key, Credential, SlotKind};
+
+mod totp;
+pub use totp::TotpConfig;
+
+mod header;
+pub use header::{VaultHeader, DEFAULT_AUTO_LOCK_MINUTES};
+
+mod index;
+pub use index::{check_index_freshness, content_mac, load_index, save_index, EncryptedFile, KeyScheme};
+
+mod stream;
+
+mod cipher;
+pub use cipher::CipherKind;
+
+mod padding;
+pub use padding::{PaddingPolicy, UnpadWriter};
+
+mod compress;
+pub use compress::{compress_reader, is_compressible, DecompressWriter, SNIFF_LEN};
+
+mod container;
+pub use container::{file_identity, generate_file_id, ContainerHeader, FileMetadata, Layout, FLAG_COMPRESSED, FLAG_PADDED};
+
+mod shred;
+pub use shred::{secure_delete, DEFAULT_SHRED_PASSES};
+
+mod codes;
+pub use codes::{code_vault_dir, codes_configured, match_code, set_unlock_code, CodeMatch};
+
+mod attempts;
+pub use attempts::{record_failed_unlock, refresh_attempts, reset_failed_unlocks, unlock_delay, AttemptsKey};
+
+mod decoy;
+
+mod duress;
+pub use duress::{clear_duress_code, destroy_vault, set_duress_code, DuressMode};
+
+mod scrub;
+pub use scrub::{find_orphans, quarantine, HashingReader, HashingSink, ScrubReport};
+
+mod rekey;
+pub use rekey::{add_key_slot, change_password, open_vault, recover_vault, remove_key_slot};
+
+// مجلد مؤقت باسم عشوائي لاختبارات الوحدات التي تكتب على القرص
+#[cfg(test)]
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    use rand::RngCore;
+    let mut suffix = [0u8; 8];
+    aes_gcm::aead::OsRng.fill_bytes(&mut suffix);
+    let dir = std::env::temp_dir().join(format!("vault-{}-{}", name, hex::encode(suffix)));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use std::io::{self, Cursor, Read, Write};
+
+// حشو اختياري يخفي الحجم الحقيقي للملف المشفر. الحمولة المحشوة داخل التدفق الموثّق:
+//   الطول الحقيقي u64 | البيانات | أصفار حتى الحجم المحشو
+// أما الحمولة المضغوطة فحجمها غير معروف قبل انتهاء الضغط، فيُلحق الحشو بعدها
+// كإطارات zstd قابلة للتخطي يتجاهلها فك الضغط:
+//   إطار zstd | (magic 0x184D2A50 u32le | الطول u32le | أصفار)...
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum PaddingPolicy {
+    #[default]
+    None,
+    // Padmé: زيادة لا تتجاوز ~12% وتُبقي O(log log n) بت فقط من الحجم
+    Padme,
+    // أقرب قوة للعدد 2 - إخفاء أقوى بتكلفة قد تصل إلى ضعف الحجم
+    PowerOfTwo,
+}
+
+const LENGTH_PREFIX: u64 = 8;
+const MIN_BUCKET: u64 = 4096;
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A50;
+const SKIPPABLE_HEADER: u64 = 8;
+const MAX_SKIPPABLE: u64 = SKIPPABLE_HEADER + u32::MAX as u64;
+
+impl PaddingPolicy {
+    pub const ALL: [PaddingPolicy; 3] = [PaddingPolicy::None, PaddingPolicy::Padme, PaddingPolicy::PowerOfTwo];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PaddingPolicy::None => "بدون حشو",
+            PaddingPolicy::Padme => "Padmé",
+            PaddingPolicy::PowerOfTwo => "قوى العدد 2",
+        }
+    }
+
+    // الحجم المحشو للحمولة كاملة (بادئة الطول + البيانات)
+    pub fn padded_len(self, len: u64) -> u64 {
+        match self {
+            PaddingPolicy::None => len,
+            PaddingPolicy::Padme => padme(len),
+            PaddingPolicy::PowerOfTwo => len.max(MIN_BUCKET).checked_next_power_of_two().unwrap_or(len),
+        }
+    }
+
+    // حجم الحمولة المحشوة لملف بحجم `size`
+    pub fn payload_len(self, size: u64) -> u64 {
+        self.padded_len(LENGTH_PREFIX + size)
+    }
+
+    pub fn pad_reader(self, reader: impl Read, size: u64) -> impl Read {
+        let padding = self.payload_len(size) - (LENGTH_PREFIX + size);
+        Cursor::new(size.to_be_bytes())
+            .chain(reader.take(size))
+            .chain(io::repeat(0).take(padding))
+    }
+
+    // حشو حمولة مضغوطة (انظر أعلى الملف) - يُحسب عند انتهاء القارئ
+    pub fn pad_compressed_reader<R: Read>(self, reader: R) -> SkippablePadReader<R> {
+        SkippablePadReader { inner: reader, policy: self, read: 0, done: false, remaining: 0, frame: Vec::new(), zeros: 0 }
+    }
+}
+
+fn padme(len: u64) -> u64 {
+    if len < 2 {
+        return len;
+    }
+    let e = 63 - len.leading_zeros() as u64;
+    let s = 64 - e.leading_zeros() as u64;
+    let mask = (1u64 << (e - s)) - 1;
+    (len + mask) & !mask
+}
+
+pub struct SkippablePadReader<R> {
+    inner: R,
+    policy: PaddingPolicy,
+    read: u64,
+    done: bool,
+    // ما بقي من الحشو كاملاً، وترويسة الإطار الحالي وأصفاره التي لم تُقرأ بعد
+    remaining: u64,
+    frame: Vec<u8>,
+    zeros: u64,
+}
+
+impl<R: Read> SkippablePadReader<R> {
+    // حجم الإطار التالي: إطار واحد غالباً، وأي باقٍ يجب أن يتسع لترويسة إطار آخر
+    fn next_frame(&mut self) {
+        let mut size = self.remaining.min(MAX_SKIPPABLE);
+        if self.remaining - size > 0 && self.remaining - size < SKIPPABLE_HEADER {
+            size -= SKIPPABLE_HEADER;
+        }
+        self.remaining -= size;
+        self.zeros = size - SKIPPABLE_HEADER;
+        self.frame.clear();
+        self.frame.extend_from_slice(&SKIPPABLE_MAGIC.to_le_bytes());
+        self.frame.extend_from_slice(&(self.zeros as u32).to_le_bytes());
+    }
+}
+
+impl<R: Read> Read for SkippablePadReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if !self.done {
+            let read = self.inner.read(buf)?;
+            if read > 0 {
+                self.read += read as u64;
+                return Ok(read);
+            }
+            // حجز مكان ترويسة إطار واحد على الأقل حتى يكون الحشو إطاراً صالحاً
+            self.done = true;
+            self.remaining = self.policy.padded_len(self.read + SKIPPABLE_HEADER) - self.read;
+            if self.policy == PaddingPolicy::None {
+                self.remaining = 0;
+            }
+        }
+        if self.frame.is_empty() && self.zeros == 0 {
+            if self.remaining == 0 {
+                return Ok(0);
+            }
+            self.next_frame();
+        }
+        if !self.frame.is_empty() {
+            let take = self.frame.len().min(buf.len());
+            buf[..take].copy_from_slice(&self.frame[..take]);
+            self.frame.drain(..take);
+            return Ok(take);
+        }
+        let take = self.zeros.min(buf.len() as u64) as usize;
+        buf[..take].fill(0);
+        self.zeros -= take as u64;
+        Ok(take)
+    }
+}
+
+// يكتب الطول الحقيقي فقط من الحمولة المفكوكة ويتجاهل الحشو
+pub struct UnpadWriter<W: Write> {
+    inner: W,
+    prefix: Vec<u8>,
+    remaining: Option<u64>,
+}
+
+impl<W: Write> UnpadWriter<W> {
+    pub fn new(inner: W) -> Self {
+        UnpadWriter { inner, prefix: Vec::with_capacity(8), remaining: None }
+    }
+
+    // الحمولة يجب أن تحتوي الطول المعلن كاملاً - وإلا فالملف تالف
+    pub fn finish(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.remaining != Some(0) {
+            return Err("خطأ في فك التشفير: حجم الملف لا يطابق المعلن".into());
+        }
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for UnpadWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        if self.remaining.is_none() {
+            let take = (8 - self.prefix.len()).min(rest.len());
+            self.prefix.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            if self.prefix.len() == 8 {
+                self.remaining = Some(u64::from_be_bytes(self.prefix[..].try_into().unwrap()));
+            }
+        }
+        if let Some(remaining) = self.remaining.as_mut() {
+            let take = (*remaining).min(rest.len() as u64) as usize;
+            self.inner.write_all(&rest[..take])?;
+            *remaining -= take as u64;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::compress::{compress_reader, DecompressWriter};
+
+    const SIZES: [u64; 9] = [0, 1, 7, 8, 4095, 4096, 4097, 65_535, 300_000];
+
+    fn sample(len: u64) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    // كتابات بأحجام متفاوتة تقسم بادئة الطول والبيانات في مواضع مختلفة
+    fn write_in_pieces(mut writer: impl Write, data: &[u8]) {
+        let mut rest = data;
+        for size in [1, 7, 8, 13, 4096].into_iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (piece, tail) = rest.split_at(size.min(rest.len()));
+            writer.write_all(piece).unwrap();
+            rest = tail;
+        }
+    }
+
+    #[test]
+    fn padded_roundtrip_every_policy() {
+        for policy in PaddingPolicy::ALL {
+            for size in SIZES {
+                let data = sample(size);
+                let mut padded = Vec::new();
+                policy.pad_reader(&data[..], size).read_to_end(&mut padded).unwrap();
+                assert_eq!(padded.len() as u64, policy.payload_len(size));
+
+                let mut plain = Vec::new();
+                let mut writer = UnpadWriter::new(&mut plain);
+                write_in_pieces(&mut writer, &padded);
+                writer.finish().unwrap();
+                assert_eq!(plain, data);
+            }
+        }
+    }
+
+    #[test]
+    fn compressed_roundtrip_every_policy() {
+        for policy in PaddingPolicy::ALL {
+            for size in SIZES {
+                let data = sample(size);
+                let mut compressed = Vec::new();
+                compress_reader(&data[..]).unwrap().read_to_end(&mut compressed).unwrap();
+                let mut padded = Vec::new();
+                policy.pad_compressed_reader(compress_reader(&data[..]).unwrap()).read_to_end(&mut padded).unwrap();
+                let expected = match policy {
+                    PaddingPolicy::None => compressed.len() as u64,
+                    _ => policy.padded_len(compressed.len() as u64 + SKIPPABLE_HEADER),
+                };
+                assert_eq!(padded.len() as u64, expected);
+
+                let mut plain = Vec::new();
+                let mut writer = DecompressWriter::new(&mut plain).unwrap();
+                write_in_pieces(&mut writer, &padded);
+                writer.finish().unwrap();
+                assert_eq!(plain, data);
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_padding_splits_into_valid_frames() {
+        // باقٍ بعد الإطار الأكبر أقصر من ترويسة: الإطار الأول يتنازل عن مكانها
+        let mut reader = PaddingPolicy::Padme.pad_compressed_reader(io::empty());
+        reader.remaining = MAX_SKIPPABLE + 3;
+        let mut total = 0;
+        while reader.remaining > 0 {
+            reader.next_frame();
+            assert_eq!(reader.frame[..4], SKIPPABLE_MAGIC.to_le_bytes());
+            assert_eq!(u32::from_le_bytes(reader.frame[4..8].try_into().unwrap()) as u64, reader.zeros);
+            total += SKIPPABLE_HEADER + reader.zeros;
+        }
+        assert_eq!(total, MAX_SKIPPABLE + 3);
+        assert_eq!(reader.zeros, 3);
+    }
+
+    #[test]
+    fn truncated_payload_is_rejected() {
+        let data = sample(100);
+        let mut padded = Vec::new();
+        PaddingPolicy::Padme.pad_reader(&data[..], 100).read_to_end(&mut padded).unwrap();
+
+        // بيانات أقل من الطول المعلن، وبادئة طول ناقصة
+        for cut in [LENGTH_PREFIX as usize + 99, 3] {
+            let mut writer = UnpadWriter::new(Vec::new());
+            writer.write_all(&padded[..cut]).unwrap();
+            assert!(writer.finish().is_err());
+        }
+    }
+}
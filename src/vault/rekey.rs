@@ -0,0 +1,246 @@
+use std::path::Path;
+
+use super::duress::rekey_owner;
+use super::header::VaultHeader;
+use super::index::{content_mac, load_index, rekey_counter, save_index, KeyScheme};
+use super::keys::{generate_key, unwrap_key, wrap_key};
+use super::secret::SecretKey;
+use super::slots::{Credential, KeySlot, SlotKind};
+
+// فتح الخزنة بأي فتحة تقبل بيانات الاعتماد، وإكمال أي تدوير مفاتيح انقطع في المرة السابقة
+pub fn open_vault(vault_dir: &Path, credential: &Credential) -> Result<SecretKey, Box<dyn std::error::Error>> {
+    let header = VaultHeader::load(vault_dir).ok_or("الخزنة غير موجودة")?;
+    let (index, kek, master_key) = header.unlock(credential)?;
+
+    if let Some(previous_key) = header.slots[index].unwrap_pending(&kek)? {
+        finish_rotation(vault_dir, header, &previous_key, &master_key)?;
+    }
+    Ok(master_key)
+}
+
+// تغيير كلمة المرور. إن كانت الفتحة الوحيدة يُدوَّر المفتاح الرئيسي أيضاً:
+// 1) ترويسة جديدة تحمل المفتاح القديم مغلفاً بكلمة المرور الجديدة (pending)
+// 2) فهرس جديد بمفاتيح البيانات معاد تغليفها بالمفتاح الجديد
+// 3) حذف المفتاح القديم من الترويسة
+// كل خطوة كتابة ذرية، وأي انقطاع بينها يبقي كل ملف قابلاً للفتح بأحد المفتاحين.
+// مع وجود فتحات أخرى يُعاد تغليف هذه الفتحة فقط - التدوير كان سيقفل أصحاب الفتحات الأخرى
+pub fn change_password(
+    vault_dir: &Path,
+    old_password: &str,
+    new_password: &str,
+) -> Result<SecretKey, Box<dyn std::error::Error>> {
+    let mut header = VaultHeader::load(vault_dir).ok_or("الخزنة غير موجودة")?;
+    let (index, old_kek, old_key) = header.unlock(&Credential::Secret(old_password))?;
+    if header.slots[index].kind != SlotKind::Password {
+        return Err("كلمة المرور الحالية غير صحيحة".into());
+    }
+
+    // تدوير سابق لم يكتمل: الملفات التي لم يعد تغليفها ما زالت بالمفتاح الأقدم
+    if let Some(previous_key) = header.slots[index].unwrap_pending(&old_kek)? {
+        finish_rotation(vault_dir, header.clone(), &previous_key, &old_key)?;
+        header = VaultHeader::load(vault_dir).ok_or("الخزنة غير موجودة")?;
+    }
+
+    // الاسم وملف المفتاح المشترط يبقيان كما هما
+    let KeySlot { label, keyfile, .. } = header.slots[index].clone();
+    let new_credential = Credential::Secret(new_password);
+    if header.slots.len() > 1 {
+        header.slots[index] = KeySlot::seal(SlotKind::Password, &label, keyfile.as_deref(), &new_credential, &old_key, None)?;
+        header.save(vault_dir)?;
+        return Ok(old_key);
+    }
+
+    let new_key = generate_key();
+    header.slots[index] = KeySlot::seal(SlotKind::Password, &label, keyfile.as_deref(), &new_credential, &new_key, Some(&old_key))?;
+    header.save(vault_dir)?;
+
+    finish_rotation(vault_dir, header, &old_key, &new_key)?;
+    Ok(new_key)
+}
+
+// الاسترداد بعبارة الاسترداد: تُضاف كلمة مرور جديدة بجانب الفتحات الموجودة،
+// والمنسية تبقى حتى يحذفها المستخدم من شاشة المفاتيح (قد تخص عضواً آخر في الفريق)
+pub fn recover_vault(
+    vault_dir: &Path,
+    recovery_phrase: &str,
+    new_password: &str,
+) -> Result<SecretKey, Box<dyn std::error::Error>> {
+    let header = VaultHeader::load(vault_dir).ok_or("الخزنة غير موجودة")?;
+    let (index, kek, master_key) = header.unlock(&Credential::Secret(recovery_phrase))
+        .map_err(|_| "عبارة الاسترداد غير صحيحة")?;
+    if header.slots[index].kind != SlotKind::Recovery {
+        return Err("عبارة الاسترداد غير صحيحة".into());
+    }
+
+    if let Some(previous_key) = header.slots[index].unwrap_pending(&kek)? {
+        finish_rotation(vault_dir, header, &previous_key, &master_key)?;
+    }
+    add_key_slot(vault_dir, &master_key, SlotKind::Password, "بعد الاسترداد", None, &Credential::Secret(new_password))?;
+    Ok(master_key)
+}
+
+// إضافة فتحة جديدة تغلف المفتاح الرئيسي الحالي - لا يُعاد تشفير أي ملف.
+// keyfile لفتحات كلمة المرور التي تشترط ملف مفتاح معها
+pub fn add_key_slot(
+    vault_dir: &Path,
+    master_key: &[u8; 32],
+    kind: SlotKind,
+    label: &str,
+    keyfile: Option<&Path>,
+    credential: &Credential,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut header = VaultHeader::load(vault_dir).ok_or("الخزنة غير موجودة")?;
+    header.slots.push(KeySlot::seal(kind, label, keyfile, credential, master_key, None)?);
+    header.save(vault_dir)
+}
+
+pub fn remove_key_slot(vault_dir: &Path, index: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let mut header = VaultHeader::load(vault_dir).ok_or("الخزنة غير موجودة")?;
+    if header.slots.len() <= 1 {
+        return Err("لا يمكن حذف آخر مفتاح للخزنة".into());
+    }
+    if index >= header.slots.len() {
+        return Err("المفتاح غير موجود".into());
+    }
+    header.slots.remove(index);
+    header.save(vault_dir)
+}
+
+fn finish_rotation(
+    vault_dir: &Path,
+    mut header: VaultHeader,
+    old_key: &[u8; 32],
+    new_key: &[u8; 32],
+) -> Result<(), Box<dyn std::error::Error>> {
+    // الفهرس قد يكون حُفظ بالمفتاح الجديد قبل الانقطاع
+    let mut files = load_index(vault_dir, new_key).or_else(|_| load_index(vault_dir, old_key))?;
+    for file in files.iter_mut().filter(|f| f.scheme == KeyScheme::Envelope) {
+        // إدخال أعيد تغليفه قبل الانقطاع يفتح بالمفتاح الجديد مباشرة
+        if unwrap_key(new_key, &file.wrapped_key).is_ok() {
+            continue;
+        }
+        let data_key = unwrap_key(old_key, &file.wrapped_key)
+            .map_err(|_| format!("تعذر فك مفتاح الملف: {}", file.name))?;
+        file.wrapped_key = wrap_key(new_key, &data_key)?;
+    }
+    // مختصرات كشف التكرار مرتبطة بالمفتاح الرئيسي أيضاً
+    for file in files.iter_mut().filter(|f| !f.sha256.is_empty()) {
+        file.content_mac = content_mac(new_key, &file.sha256);
+    }
+    // سجل الجيل موسوم بالمفتاح الرئيسي - يُنقل قبل حفظ الفهرس حتى يتابع الجيل من حيث كان
+    let _ = rekey_counter(vault_dir, old_key, new_key);
+    save_index(vault_dir, new_key, &files)?;
+    // رمز الإتلاف يبقى لهذه الخزنة - بأفضل جهد لأن فشله لا يمس بيانات الخزنة
+    let _ = rekey_owner(old_key, new_key);
+
+    for slot in header.slots.iter_mut() {
+        slot.pending_key = None;
+    }
+    header.save(vault_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::cipher::CipherKind;
+    use super::super::index::EncryptedFile;
+    use super::super::slots::generate_recovery_key;
+    use super::super::temp_dir;
+    use std::fs;
+
+    fn envelope_entry(master_key: &[u8; 32], data_key: &[u8; 32]) -> EncryptedFile {
+        EncryptedFile {
+            file_id: "id".to_string(),
+            name: "a.txt".to_string(),
+            original_extension: "txt".to_string(),
+            encrypted_path: "a.secure".to_string(),
+            size: 1,
+            created_at: String::new(),
+            scheme: KeyScheme::Envelope,
+            wrapped_key: wrap_key(master_key, data_key).unwrap(),
+            sha256: String::new(),
+            content_mac: String::new(),
+            blob_name: None,
+            migration_error: None,
+        }
+    }
+
+    // الخطوة 1 من التدوير وحدها: الترويسة بالمفتاح الجديد والقديم معلقاً، ثم انقطاع
+    fn interrupt_rotation(dir: &Path, old_key: &[u8; 32]) -> SecretKey {
+        let mut header = VaultHeader::load(dir).unwrap();
+        let new_key = generate_key();
+        header.slots[0] = KeySlot::seal(SlotKind::Password, "", None, &Credential::Secret("password"), &new_key, Some(old_key)).unwrap();
+        header.save(dir).unwrap();
+        new_key
+    }
+
+    fn assert_rotated(dir: &Path, new_key: &[u8; 32], data_key: &[u8; 32]) {
+        assert_eq!(*open_vault(dir, &Credential::Secret("password")).unwrap(), *new_key);
+        let files = load_index(dir, new_key).unwrap();
+        assert_eq!(*unwrap_key(new_key, &files[0].wrapped_key).unwrap(), *data_key);
+        assert!(VaultHeader::load(dir).unwrap().slots.iter().all(|slot| slot.pending_key.is_none()));
+    }
+
+    #[test]
+    fn interrupted_rotation_finishes_on_open() {
+        let dir = temp_dir("rotation");
+        let old_key = VaultHeader::create(&dir, "password", CipherKind::default()).unwrap();
+        let data_key = generate_key();
+        save_index(&dir, &old_key, &[envelope_entry(&old_key, &data_key)]).unwrap();
+
+        let new_key = interrupt_rotation(&dir, &old_key);
+        assert_rotated(&dir, &new_key, &data_key);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotation_interrupted_after_the_index_finishes_on_open() {
+        let dir = temp_dir("rotation-index");
+        let old_key = VaultHeader::create(&dir, "password", CipherKind::default()).unwrap();
+        let data_key = generate_key();
+        save_index(&dir, &old_key, &[envelope_entry(&old_key, &data_key)]).unwrap();
+
+        // الخطوة 2 اكتملت أيضاً: الفهرس محفوظ بالمفتاح الجديد والانقطاع قبل مسح المفتاح القديم
+        let new_key = interrupt_rotation(&dir, &old_key);
+        save_index(&dir, &new_key, &[envelope_entry(&new_key, &data_key)]).unwrap();
+        assert_rotated(&dir, &new_key, &data_key);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recovery_phrase_adds_a_password() {
+        let dir = temp_dir("recover");
+        let master_key = VaultHeader::create(&dir, "forgotten", CipherKind::default()).unwrap();
+        let phrase = generate_recovery_key();
+        add_key_slot(&dir, &master_key, SlotKind::Recovery, "", None, &Credential::Secret(&phrase)).unwrap();
+
+        // العبارة تُقبل بأي حالة أحرف ومسافات
+        let typed = format!("  {}\n", phrase.to_uppercase().replace(' ', "   "));
+        let recovered = recover_vault(&dir, &typed, "new password").unwrap();
+        assert_eq!(*recovered, *master_key);
+        assert_eq!(*open_vault(&dir, &Credential::Secret("new password")).unwrap(), *master_key);
+        // الفتحة المنسية تبقى حتى يحذفها المستخدم
+        assert!(open_vault(&dir, &Credential::Secret("forgotten")).is_ok());
+        assert_eq!(VaultHeader::load(&dir).unwrap().slots.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_the_right_phrase_recovers() {
+        let dir = temp_dir("wrong");
+        let master_key = VaultHeader::create(&dir, "password", CipherKind::default()).unwrap();
+        let phrase = generate_recovery_key();
+        add_key_slot(&dir, &master_key, SlotKind::Recovery, "", None, &Credential::Secret(&phrase)).unwrap();
+
+        assert!(recover_vault(&dir, &generate_recovery_key(), "x").is_err());
+        // مجموع تحقق خاطئ، وصيغة hex، وكلمة مرور الخزنة نفسها
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        let last = words.len() - 1;
+        words[last] = if words[last] == "zoo" { "abandon" } else { "zoo" };
+        assert!(recover_vault(&dir, &words.join(" "), "x").is_err());
+        assert!(recover_vault(&dir, &hex::encode(*master_key), "x").is_err());
+        assert!(recover_vault(&dir, "password", "x").is_err());
+        assert_eq!(VaultHeader::load(&dir).unwrap().slots.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
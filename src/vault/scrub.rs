@@ -0,0 +1,103 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::index::EncryptedFile;
+
+const QUARANTINE_DIR: &str = "quarantine";
+
+// يحسب SHA-256 للنص الأصلي أثناء مروره إلى التشفير - دون قراءة الملف مرتين
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    len: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        HashingReader { inner, hasher: Sha256::new(), len: 0 }
+    }
+
+    // عدد بايتات النص الأصلي التي مرت حتى الآن
+    pub fn bytes_read(&self) -> u64 {
+        self.len
+    }
+
+    pub fn finish(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        self.len += read as u64;
+        Ok(read)
+    }
+}
+
+// الوجهة المقابلة عند الفحص: النص المفكوك يُختصر ولا يُكتب في أي مكان
+#[derive(Default)]
+pub struct HashingSink {
+    hasher: Sha256,
+}
+
+impl HashingSink {
+    pub fn finish(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+impl Write for HashingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// نتيجة فحص الخزنة. الأسماء للعرض فقط، والتعرف على الإدخالات بمسار ملفها المشفر
+#[derive(Clone, Default)]
+pub struct ScrubReport {
+    pub checked: usize,
+    // إدخالات فشل فك ملفها أو لم يطابق مختصرها المحفوظ: (المسار المشفر، الاسم، السبب)
+    pub damaged: Vec<(String, String, String)>,
+    // إدخالات ملفها المشفر غير موجود: (المسار المشفر، الاسم)
+    pub missing: Vec<(String, String)>,
+    // ملفات .secure لا يشير إليها أي إدخال
+    pub orphans: Vec<PathBuf>,
+}
+
+impl ScrubReport {
+    pub fn is_healthy(&self) -> bool {
+        self.damaged.is_empty() && self.missing.is_empty() && self.orphans.is_empty()
+    }
+}
+
+// ملفات .secure في مجلد الخزنة التي لا يشير إليها الفهرس
+pub fn find_orphans(vault_dir: &Path, files: &[EncryptedFile]) -> io::Result<Vec<PathBuf>> {
+    let referenced: HashSet<&Path> = files.iter().map(|f| Path::new(&f.encrypted_path)).collect();
+    let mut orphans = Vec::new();
+    for entry in fs::read_dir(vault_dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "secure") && !referenced.contains(path.as_path()) {
+            orphans.push(path);
+        }
+    }
+    orphans.sort();
+    Ok(orphans)
+}
+
+// نقل ملف مشفر إلى مجلد الحجر بدل حذفه - يبقى لمحاولات استعادة يدوية
+pub fn quarantine(vault_dir: &Path, path: &Path) -> io::Result<()> {
+    let quarantine_dir = vault_dir.join(QUARANTINE_DIR);
+    fs::create_dir_all(&quarantine_dir)?;
+    let name = path.file_name().ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    fs::rename(path, quarantine_dir.join(name))
+}
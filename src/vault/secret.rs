@@ -0,0 +1,139 @@
+use std::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+
+// مفتاح سري من 32 بايت في الكومة بعنوان ثابت: مقفول في الذاكرة حتى لا يُكتب في ملف التبادل،
+// ويُمسح بالأصفار عند الإسقاط. القفل بأفضل جهد - قد يرفضه النظام عند تجاوز حد RLIMIT_MEMLOCK
+pub struct SecretKey {
+    _lock: Option<region::LockGuard>,
+    bytes: Box<[u8; 32]>,
+}
+
+impl SecretKey {
+    pub fn zeroed() -> Self {
+        let bytes = Box::new([0u8; 32]);
+        let lock = region::lock(bytes.as_ptr(), bytes.len()).ok();
+        SecretKey { _lock: lock, bytes }
+    }
+
+    pub fn from_slice(source: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        if source.len() != 32 {
+            return Err("طول مفتاح غير صالح".into());
+        }
+        let mut key = Self::zeroed();
+        key.bytes.copy_from_slice(source);
+        Ok(key)
+    }
+}
+
+impl Deref for SecretKey {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &[u8; 32] {
+        &self.bytes
+    }
+}
+
+impl DerefMut for SecretKey {
+    fn deref_mut(&mut self) -> &mut [u8; 32] {
+        &mut self.bytes
+    }
+}
+
+impl Clone for SecretKey {
+    fn clone(&self) -> Self {
+        let mut key = Self::zeroed();
+        key.bytes.copy_from_slice(&*self.bytes);
+        key
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+// مخزن مؤقت للنص المفكوك بسعة ثابتة يُقفل عند إنشائه ويُمسح بالكامل عند الإسقاط.
+// يجب ألا يتجاوز المحتوى السعة الأولى، وإلا أعاد Vec الحجز خارج المنطقة المقفولة
+pub struct SecretBuffer {
+    _lock: Option<region::LockGuard>,
+    bytes: Vec<u8>,
+}
+
+impl SecretBuffer {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let bytes = Vec::with_capacity(capacity);
+        let lock = region::lock(bytes.as_ptr(), capacity.max(1)).ok();
+        SecretBuffer { _lock: lock, bytes }
+    }
+}
+
+impl Deref for SecretBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.bytes
+    }
+}
+
+impl DerefMut for SecretBuffer {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.bytes
+    }
+}
+
+impl Drop for SecretBuffer {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+// يمنع تفريغ الذاكرة (core dump) طالما الخزنة مفتوحة، ويعيد الإعداد السابق عند الإسقاط
+pub struct CoreDumpGuard {
+    #[cfg(unix)]
+    previous: Option<libc::rlimit>,
+}
+
+pub fn suppress_core_dumps() -> CoreDumpGuard {
+    #[cfg(unix)]
+    {
+        let mut current = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        // SAFETY: استدعاءات نظام بمؤشرات لبنى محلية صالحة
+        let previous = unsafe {
+            if libc::getrlimit(libc::RLIMIT_CORE, &mut current) == 0 {
+                let disabled = libc::rlimit { rlim_cur: 0, rlim_max: current.rlim_max };
+                libc::setrlimit(libc::RLIMIT_CORE, &disabled);
+                Some(current)
+            } else {
+                None
+            }
+        };
+        #[cfg(target_os = "linux")]
+        // SAFETY: PR_SET_DUMPABLE لا يقرأ أي مؤشر
+        unsafe {
+            libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0);
+        }
+        CoreDumpGuard { previous }
+    }
+    #[cfg(not(unix))]
+    CoreDumpGuard {}
+}
+
+impl Drop for CoreDumpGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            if let Some(previous) = self.previous {
+                // SAFETY: مؤشر لبنية محلية صالحة
+                unsafe {
+                    libc::setrlimit(libc::RLIMIT_CORE, &previous);
+                }
+            }
+            #[cfg(target_os = "linux")]
+            // SAFETY: PR_SET_DUMPABLE لا يقرأ أي مؤشر
+            unsafe {
+                libc::prctl(libc::PR_SET_DUMPABLE, 1, 0, 0, 0);
+            }
+        }
+    }
+}
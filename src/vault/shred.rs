@@ -0,0 +1,186 @@
+use aes_gcm::aead::OsRng;
+use rand::RngCore;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+// عدد مرات الكتابة الافتراضي - مرة واحدة بعشوائيات تكفي على الأقراص الحديثة
+pub const DEFAULT_SHRED_PASSES: u32 = 1;
+
+// حذف آمن: الكتابة فوق المحتوى بعشوائيات بعدد المرات المطلوب، ثم تصفير الطول، ثم إعادة
+// التسمية باسم عشوائي حتى لا يبقى الاسم الأصلي في مدخلات المجلد، ثم الحذف.
+// يعيد تحذيراً إن كان نظام الملفات لا يكتب فوق الكتل القديمة في مكانها.
+// أقراص SSD قد تحتفظ بكتل قديمة بسبب توزيع الكتابة ولا سبيل لكشف ذلك من هنا
+pub fn secure_delete(path: &Path, passes: u32) -> io::Result<Option<String>> {
+    let warning = if passes > 0 { overwrite_caveat(path) } else { None };
+
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let len = file.metadata()?.len();
+    overwrite(&mut file, len, passes)?;
+    file.set_len(0)?;
+    file.sync_all()?;
+    drop(file);
+
+    let mut name = [0u8; 12];
+    OsRng.fill_bytes(&mut name);
+    let renamed = path.with_file_name(hex::encode(name));
+    match fs::rename(path, &renamed) {
+        Ok(()) => fs::remove_file(&renamed)?,
+        Err(_) => fs::remove_file(path)?,
+    }
+    Ok(warning)
+}
+
+// حذف فوري والكتابة فوق المحتوى في الخلفية، لمن لا يحتمل انتظار ملفات كبيرة. على يونكس يُحذف
+// الاسم فوراً ويُكتب فوق الكتل عبر المقبض المفتوح، وفي غيره يُعاد تسمية الملف باسم عشوائي أولاً.
+// كل الملفات تُكتب فوقها بالتتابع في خيط واحد مهما كان عددها. ملف يتعذر فصله لا يوقف البقية،
+// ويُعاد أول خطأ. إغلاق التطبيق قبل انتهاء الكتابة يحرر الكتل دون الكتابة فوقها
+pub fn detach_and_shred(paths: &[PathBuf], passes: u32) -> io::Result<()> {
+    let mut detached = Vec::with_capacity(paths.len());
+    let mut first_error = None;
+    for path in paths {
+        match detach(path) {
+            Ok(file) => detached.push(file),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if !detached.is_empty() {
+        thread::spawn(move || {
+            for file in detached {
+                #[cfg(unix)]
+                {
+                    let (mut file, len) = file;
+                    let _ = overwrite(&mut file, len, passes);
+                }
+                #[cfg(not(unix))]
+                let _ = secure_delete(&file, passes);
+            }
+        });
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+#[cfg(unix)]
+fn detach(path: &Path) -> io::Result<(fs::File, u64)> {
+    let file = OpenOptions::new().write(true).open(path)?;
+    let len = file.metadata()?.len();
+    fs::remove_file(path)?;
+    Ok((file, len))
+}
+
+#[cfg(not(unix))]
+fn detach(path: &Path) -> io::Result<PathBuf> {
+    let mut name = [0u8; 12];
+    OsRng.fill_bytes(&mut name);
+    let renamed = path.with_file_name(hex::encode(name));
+    fs::rename(path, &renamed)?;
+    Ok(renamed)
+}
+
+fn overwrite(file: &mut fs::File, len: u64, passes: u32) -> io::Result<()> {
+    let mut buffer = vec![0u8; 64 * 1024];
+    for _ in 0..passes {
+        file.seek(SeekFrom::Start(0))?;
+        let mut remaining = len;
+        while remaining > 0 {
+            let take = remaining.min(buffer.len() as u64) as usize;
+            OsRng.fill_bytes(&mut buffer[..take]);
+            file.write_all(&buffer[..take])?;
+            remaining -= take as u64;
+        }
+        // كل مرة تصل القرص قبل التالية - وإلا قد تُدمج في ذاكرة النظام المؤقتة ولا يُكتب إلا آخرها
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+// أنظمة النسخ عند الكتابة أو السجلية تكتب البيانات الجديدة في كتل جديدة وتترك القديمة
+#[cfg(target_os = "linux")]
+fn overwrite_caveat(path: &Path) -> Option<String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    const BTRFS: u32 = 0x9123_683E;
+    const ZFS: u32 = 0x2FC1_2FC1;
+    const BCACHEFS: u32 = 0xCA45_1A4E;
+    const F2FS: u32 = 0xF2F5_2010;
+    const NILFS: u32 = 0x3434;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let name = match stat.f_type as u32 {
+        BTRFS => "btrfs",
+        ZFS => "ZFS",
+        BCACHEFS => "bcachefs",
+        F2FS => "F2FS",
+        NILFS => "NILFS",
+        _ => return None,
+    };
+    Some(caveat(name))
+}
+
+#[cfg(target_os = "macos")]
+fn overwrite_caveat(path: &Path) -> Option<String> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) }.to_string_lossy();
+    (name == "apfs" || name == "zfs").then(|| caveat(&name))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn overwrite_caveat(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn caveat(filesystem: &str) -> String {
+    format!(
+        "نظام الملفات ({}) لا يكتب فوق البيانات في مكانها (نسخ عند الكتابة) - لا يمكن ضمان محو المحتوى الأصلي من القرص رغم الكتابة فوقه",
+        filesystem
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::temp_dir;
+
+    #[test]
+    fn secure_delete_leaves_no_file() {
+        let dir = temp_dir("shred");
+        let path = dir.join("secret.txt");
+        fs::write(&path, vec![7u8; 100_000]).unwrap();
+        secure_delete(&path, 2).unwrap();
+        assert!(!path.exists());
+        // ولا الاسم العشوائي الذي أُعيدت التسمية إليه
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn detached_names_disappear_at_once() {
+        let dir = temp_dir("detach");
+        let paths: Vec<PathBuf> = (0..3).map(|i| dir.join(format!("{}.secure", i))).collect();
+        for path in &paths {
+            fs::write(path, b"legacy").unwrap();
+        }
+        let missing = dir.join("missing.secure");
+        let all = [paths.clone(), vec![missing]].concat();
+        // ملف مفقود يُبلغ عنه ولا يمنع البقية
+        assert!(detach_and_shred(&all, 1).is_err());
+        assert!(paths.iter().all(|path| !path.exists()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
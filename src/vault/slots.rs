@@ -0,0 +1,190 @@
+use aes_gcm::aead::OsRng;
+use bip39::Mnemonic;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+use super::kdf::{derive_key, KdfParams};
+use super::keys::{generate_key, unwrap_key, wrap_key};
+use super::secret::SecretKey;
+
+// نوع بيانات الاعتماد التي تفتح الفتحة - للعرض ولاختيار الفتحات التي تُجرب فقط
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SlotKind {
+    #[default]
+    Password,
+    Recovery,
+    Keyfile,
+}
+
+impl SlotKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            SlotKind::Password => "🔑 كلمة مرور",
+            SlotKind::Recovery => "🛟 مفتاح استرداد",
+            SlotKind::Keyfile => "📄 ملف مفتاح",
+        }
+    }
+}
+
+// ما يقدمه المستخدم لفتح الخزنة: نص مكتوب (كلمة مرور أو مفتاح استرداد) أو مسار ملف مفتاح
+pub enum Credential<'a> {
+    Secret(&'a str),
+    Keyfile(&'a Path),
+}
+
+impl Credential<'_> {
+    // البايتات التي تدخل الاشتقاق لهذه الفتحة، أو None إن كانت الفتحة لا تقبل هذا الاعتماد
+    fn material(&self, slot: &KeySlot) -> Result<Option<Zeroizing<Vec<u8>>>, Box<dyn std::error::Error>> {
+        Ok(match (self, slot.kind) {
+            // ملف المفتاح المشترط يدخل الاشتقاق مع كلمة المرور: SHA-256(الملف) || كلمة المرور
+            (Credential::Secret(text), SlotKind::Password) => {
+                let mut material = Zeroizing::new(Vec::with_capacity(32 + text.len()));
+                if let Some(keyfile) = &slot.keyfile {
+                    if !keyfile.is_file() {
+                        return Err(format!(
+                            "ملف المفتاح غير موجود: {} - صِل وسيط التخزين الذي يحمله ثم حاول مجدداً",
+                            keyfile.display()
+                        ).into());
+                    }
+                    material.extend_from_slice(&hash_keyfile(keyfile)?);
+                }
+                material.extend_from_slice(text.as_bytes());
+                Some(material)
+            }
+            (Credential::Secret(text), SlotKind::Recovery) => recovery_material(text),
+            (Credential::Keyfile(path), SlotKind::Keyfile) => Some(hash_keyfile(path)?),
+            _ => None,
+        })
+    }
+}
+
+// فتحة مفتاح: المفتاح الرئيسي مغلفاً ببيانات اعتماد واحدة، بملح ومعاملات اشتقاق خاصة بها.
+// إضافة فتحة أو حذفها لا تمس الملفات لأن المفتاح الرئيسي نفسه لا يتغير
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeySlot {
+    #[serde(default)]
+    pub kind: SlotKind,
+    #[serde(default)]
+    pub label: String,
+    pub kdf: KdfParams,
+    pub salt: String,
+    pub wrapped_key: String,
+    // ملف مفتاح مشترط مع كلمة المرور - يُحفظ مساره فقط، ومحتواه لا يغادر الوسيط الذي يحمله
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyfile: Option<PathBuf>,
+    // المفتاح الرئيسي السابق أثناء تدوير لم يكتمل، مغلفاً بنفس بيانات الاعتماد
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_key: Option<String>,
+}
+
+impl KeySlot {
+    pub fn seal(
+        kind: SlotKind,
+        label: &str,
+        keyfile: Option<&Path>,
+        credential: &Credential,
+        master_key: &[u8; 32],
+        pending: Option<&[u8; 32]>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if keyfile.is_some() && kind != SlotKind::Password {
+            return Err("ملف المفتاح الإضافي يُشترط مع كلمة المرور فقط".into());
+        }
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let mut slot = KeySlot {
+            kind,
+            label: label.to_string(),
+            kdf: KdfParams::default(),
+            salt: hex::encode(salt),
+            wrapped_key: String::new(),
+            keyfile: keyfile.map(Path::to_path_buf),
+            pending_key: None,
+        };
+
+        let kek = slot.derive_kek(credential)?.ok_or("بيانات الاعتماد لا تناسب نوع المفتاح")?;
+        slot.wrapped_key = wrap_key(&kek, master_key)?;
+        slot.pending_key = pending.map(|key| wrap_key(&kek, key)).transpose()?;
+        Ok(slot)
+    }
+
+    // فتحة خزنة وهمية (انظر decoy.rs): مفتاح عشوائي مغلف بمفتاح عشوائي أُسقط، بنفس شكل فتحة
+    // كلمة مرور حقيقية دون اشتقاق - لا تفتحها أي كلمة مرور
+    pub(super) fn decoy() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Ok(KeySlot {
+            kind: SlotKind::Password,
+            label: String::new(),
+            kdf: KdfParams::default(),
+            salt: hex::encode(salt),
+            wrapped_key: wrap_key(&generate_key(), &generate_key())?,
+            keyfile: None,
+            pending_key: None,
+        })
+    }
+
+    // مفتاح التغليف لهذه الفتحة، أو None إن كان الاعتماد من نوع آخر
+    pub fn derive_kek(&self, credential: &Credential) -> Result<Option<SecretKey>, Box<dyn std::error::Error>> {
+        let Some(material) = credential.material(self)? else {
+            return Ok(None);
+        };
+        let salt = hex::decode(&self.salt)?;
+        derive_key(&material, &salt, &self.kdf).map(Some)
+    }
+
+    // نجاح فك تغليف المفتاح الرئيسي هو نفسه التحقق من بيانات الاعتماد
+    pub fn unwrap_master(&self, kek: &[u8; 32]) -> Result<SecretKey, Box<dyn std::error::Error>> {
+        unwrap_key(kek, &self.wrapped_key)
+    }
+
+    pub fn unwrap_pending(&self, kek: &[u8; 32]) -> Result<Option<SecretKey>, Box<dyn std::error::Error>> {
+        self.pending_key.as_deref().map(|wrapped| unwrap_key(kek, wrapped)).transpose()
+    }
+
+    // إتلاف الفتحة: ملح جديد ومفتاح مغلف عشوائي بنفس الطول - تبدو كما كانت ولا يفتحها شيء
+    pub fn scramble(&mut self) {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        self.salt = hex::encode(salt);
+        let mut wrapped = vec![0u8; self.wrapped_key.len() / 2];
+        OsRng.fill_bytes(&mut wrapped);
+        self.wrapped_key = hex::encode(wrapped);
+        self.pending_key = None;
+    }
+}
+
+// عبارة استرداد من 24 كلمة (قائمة BIP39 الإنجليزية) تحمل 256 بت عشوائية، تُعرض مرة واحدة
+pub fn generate_recovery_key() -> Zeroizing<String> {
+    let mut entropy = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(&mut *entropy);
+    let mnemonic = Mnemonic::from_entropy(&*entropy).expect("32 بايت طول صالح لـ BIP39");
+    Zeroizing::new(mnemonic.to_string())
+}
+
+// العبارة تُقبل بأي حالة أحرف ومسافات، ويُشتق المفتاح من عشوائيتها بعد التحقق من المجموع.
+// ما لا يُقرأ كعبارة صالحة لا يُجرَّب على فتحات الاسترداد أصلاً
+fn recovery_material(text: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let words = Zeroizing::new(text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase());
+    let mnemonic = Mnemonic::parse_normalized(&words).ok()?;
+    Some(Zeroizing::new(mnemonic.to_entropy()))
+}
+
+// محتوى ملف المفتاح يُختصر بـ SHA-256 قطعةً قطعة - أي ملف بأي حجم يصلح
+fn hash_keyfile(path: &Path) -> Result<Zeroizing<Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut file = fs::File::open(path).map_err(|_| format!("تعذر قراءة ملف المفتاح: {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = Zeroizing::new(vec![0u8; 64 * 1024]);
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(Zeroizing::new(hasher.finalize().to_vec()))
+}
@@ -0,0 +1,192 @@
+// generic-array 0.14 يعلن نفسه مهملاً، لكن aead 0.5 ما زال يطلب ArrayLength في قيود الأنواع
+#![allow(deprecated)]
+
+use aes_gcm::aead::generic_array::ArrayLength;
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32, Nonce, StreamBE32};
+use aes_gcm::aead::consts::U5;
+use aes_gcm::aead::{AeadInPlace, KeyInit, OsRng};
+use rand::RngCore;
+use std::io::{Read, Write};
+use std::ops::Sub;
+
+use super::secret::SecretBuffer;
+
+// تشفير متدفق بصيغة STREAM: كل قطعة لها nonce = بادئة عشوائية || عداد || علم القطعة الأخيرة
+// فلا يُحمّل الملف كاملاً في الذاكرة، ويُكشف حذف القطع من النهاية أو تبديل ترتيبها.
+// البيانات المرفقة (aad) تُوثّق مع كل قطعة - تستخدمها الحاوية لربط الترويسة بالمحتوى.
+// الدوال عامة على خوارزمية AEAD؛ طول البادئة = طول nonce الخوارزمية ناقص 5 بايت
+// قطع النص الأصلي تمر في مخازن مقفولة في الذاكرة تُمسح عند الانتهاء
+pub const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+
+// يعيد عدد بايتات النص الأصلي
+pub fn encrypt_stream<A>(
+    cipher: A,
+    chunk_size: usize,
+    aad: &[u8],
+    mut reader: impl Read,
+    mut writer: impl Write,
+) -> Result<u64, Box<dyn std::error::Error>>
+where
+    A: AeadInPlace + KeyInit,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+{
+    let mut prefix = Nonce::<A, StreamBE32<A>>::default();
+    OsRng.fill_bytes(&mut prefix);
+    writer.write_all(&prefix)?;
+
+    let mut encryptor = EncryptorBE32::from_aead(cipher, &prefix);
+
+    let mut total = 0u64;
+    let mut chunk = SecretBuffer::with_capacity(chunk_size + TAG_SIZE);
+    let mut next = SecretBuffer::with_capacity(chunk_size + TAG_SIZE);
+    read_chunk(&mut reader, &mut chunk, chunk_size)?;
+
+    // نقرأ القطعة التالية مسبقاً لنعرف إن كانت الحالية هي الأخيرة
+    loop {
+        total += chunk.len() as u64;
+        if chunk.len() == chunk_size {
+            read_chunk(&mut reader, &mut next, chunk_size)?;
+        } else {
+            next.clear();
+        }
+
+        if next.is_empty() {
+            encryptor.encrypt_last_in_place(aad, &mut *chunk)
+                .map_err(|e| format!("خطأ في التشفير: {:?}", e))?;
+            writer.write_all(&chunk)?;
+            break;
+        }
+
+        encryptor.encrypt_next_in_place(aad, &mut *chunk)
+            .map_err(|e| format!("خطأ في التشفير: {:?}", e))?;
+        writer.write_all(&chunk)?;
+        std::mem::swap(&mut chunk, &mut next);
+    }
+
+    writer.flush()?;
+    Ok(total)
+}
+
+pub fn decrypt_stream<A>(
+    cipher: A,
+    chunk_size: usize,
+    aad: &[u8],
+    mut reader: impl Read,
+    mut writer: impl Write,
+) -> Result<u64, Box<dyn std::error::Error>>
+where
+    A: AeadInPlace + KeyInit,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+{
+    let mut prefix = Nonce::<A, StreamBE32<A>>::default();
+    reader.read_exact(&mut prefix).map_err(|_| "ملف غير صالح")?;
+
+    let mut decryptor = DecryptorBE32::from_aead(cipher, &prefix);
+
+    let sealed_size = chunk_size + TAG_SIZE;
+    let mut total = 0u64;
+    let mut chunk = SecretBuffer::with_capacity(sealed_size);
+    let mut next = SecretBuffer::with_capacity(sealed_size);
+    read_chunk(&mut reader, &mut chunk, sealed_size)?;
+
+    loop {
+        if chunk.len() == sealed_size {
+            read_chunk(&mut reader, &mut next, sealed_size)?;
+        } else {
+            next.clear();
+        }
+
+        // قطعة بُترت بعدها البيانات لن تُفك كقطعة أخيرة لأن علمها في الـ nonce مختلف
+        if next.is_empty() {
+            decryptor.decrypt_last_in_place(aad, &mut *chunk)
+                .map_err(|_| "خطأ في فك التشفير: الملف تالف أو مبتور")?;
+            total += chunk.len() as u64;
+            writer.write_all(&chunk)?;
+            break;
+        }
+
+        decryptor.decrypt_next_in_place(aad, &mut *chunk)
+            .map_err(|_| "خطأ في فك التشفير: الملف تالف أو مبتور")?;
+        total += chunk.len() as u64;
+        writer.write_all(&chunk)?;
+        std::mem::swap(&mut chunk, &mut next);
+    }
+
+    writer.flush()?;
+    Ok(total)
+}
+
+// قراءة حتى `size` بايت أو نهاية الملف
+fn read_chunk(reader: &mut impl Read, buffer: &mut Vec<u8>, size: usize) -> std::io::Result<()> {
+    buffer.clear();
+    reader.take(size as u64).read_to_end(buffer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes_gcm::Aes256Gcm;
+    use chacha20poly1305::XChaCha20Poly1305;
+
+    const KEY: [u8; 32] = [7u8; 32];
+    const CHUNK: usize = 16;
+
+    fn seal<A>(data: &[u8], aad: &[u8]) -> Vec<u8>
+    where
+        A: AeadInPlace + KeyInit,
+        A::NonceSize: Sub<U5>,
+        <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+    {
+        let mut sealed = Vec::new();
+        encrypt_stream(A::new_from_slice(&KEY).unwrap(), CHUNK, aad, data, &mut sealed).unwrap();
+        sealed
+    }
+
+    fn open<A>(sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>>
+    where
+        A: AeadInPlace + KeyInit,
+        A::NonceSize: Sub<U5>,
+        <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+    {
+        let mut plain = Vec::new();
+        decrypt_stream(A::new_from_slice(&KEY).unwrap(), CHUNK, aad, sealed, &mut plain)?;
+        Ok(plain)
+    }
+
+    #[test]
+    fn roundtrip_any_length() {
+        // فارغ، أقل من قطعة، مضاعف القطعة بالضبط، وقطعة أخيرة ناقصة
+        for len in [0, 5, CHUNK, 2 * CHUNK, 2 * CHUNK + 3] {
+            let data: Vec<u8> = (0..len as u8).collect();
+            assert_eq!(open::<Aes256Gcm>(&seal::<Aes256Gcm>(&data, b"aad"), b"aad").unwrap(), data);
+            assert_eq!(open::<XChaCha20Poly1305>(&seal::<XChaCha20Poly1305>(&data, b"aad"), b"aad").unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let data = [9u8; 2 * CHUNK];
+        let sealed = seal::<Aes256Gcm>(&data, b"aad");
+        let prefix = 12 - 5;
+        let sealed_chunk = CHUNK + TAG_SIZE;
+
+        let mut flipped = sealed.clone();
+        flipped[prefix + 3] ^= 1;
+        assert!(open::<Aes256Gcm>(&flipped, b"aad").is_err());
+
+        // حذف القطعة الأخيرة: التي قبلها لم تُشفَّر كأخيرة
+        assert!(open::<Aes256Gcm>(&sealed[..prefix + sealed_chunk], b"aad").is_err());
+
+        let mut swapped = sealed[..prefix].to_vec();
+        swapped.extend_from_slice(&sealed[prefix + sealed_chunk..]);
+        swapped.extend_from_slice(&sealed[prefix..prefix + sealed_chunk]);
+        assert!(open::<Aes256Gcm>(&swapped, b"aad").is_err());
+
+        assert!(open::<Aes256Gcm>(&sealed, b"other").is_err());
+        assert!(open::<Aes256Gcm>(&sealed[..prefix - 1], b"aad").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use aes_gcm::aead::OsRng;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// رمز تحقق زمني (RFC 6238) بإعدادات تطبيقات المصادقة الافتراضية: HMAC-SHA1، 6 أرقام، كل 30 ثانية
+const PERIOD: u64 = 30;
+const DIGITS: u32 = 6;
+// نقبل الخطوة السابقة واللاحقة أيضاً لتحمّل فرق بسيط في ساعة الجهاز
+const SKEW: u64 = 1;
+
+// السر محفوظ في ترويسة الخزنة لأن التحقق يسبق كلمة المرور. آخر خطوة مقبولة تُحفظ
+// حتى لا يُعاد استخدام رمز رآه شخص آخر خلال نافذته
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TotpConfig {
+    pub secret: String, // base32 كما يُعرض لتطبيق المصادقة
+    #[serde(default)]
+    pub last_step: u64,
+}
+
+impl TotpConfig {
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 20];
+        OsRng.fill_bytes(&mut secret);
+        TotpConfig { secret: BASE32_NOPAD.encode(&secret), last_step: 0 }
+    }
+
+    // رابط otpauth لتطبيق المصادقة - الاسم لا يذكر الخزنة
+    pub fn uri(&self) -> String {
+        format!(
+            "otpauth://totp/Calculator?secret={}&issuer=Calculator&algorithm=SHA1&digits={}&period={}",
+            self.secret, DIGITS, PERIOD
+        )
+    }
+
+    pub fn qr_svg(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let code = QrCode::new(self.uri())?;
+        Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+    }
+
+    // عند النجاح يُحدَّث last_step - على المستدعي حفظ الترويسة
+    pub fn verify(&mut self, code: &str) -> bool {
+        let code = code.trim();
+        if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return false;
+        }
+        let Ok(secret) = BASE32_NOPAD.decode(self.secret.as_bytes()) else {
+            return false;
+        };
+        let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) else {
+            return false;
+        };
+        let current = now.as_secs() / PERIOD;
+
+        for step in current.saturating_sub(SKEW)..=current + SKEW {
+            if step <= self.last_step {
+                continue;
+            }
+            // مقارنة بزمن ثابت حتى لا يكشف التوقيت عدد الأرقام الصحيحة
+            let expected = format!("{:0width$}", hotp(&secret, step), width = DIGITS as usize);
+            let diff = expected.bytes().zip(code.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+            if diff == 0 {
+                self.last_step = step;
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// RFC 4226: HMAC على العداد ثم اقتطاع ديناميكي إلى عدد الأرقام
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(secret).expect("HMAC يقبل أي طول مفتاح");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[19] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+    binary % 10u32.pow(DIGITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn rfc_4226_vectors() {
+        let expected = [755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489];
+        for (counter, code) in expected.into_iter().enumerate() {
+            assert_eq!(hotp(SECRET, counter as u64), code);
+        }
+    }
+
+    #[test]
+    fn rfc_6238_sha1_vectors() {
+        // الرموز في الملحق B بثمانية أرقام - آخر ستة منها هي رموزنا
+        for (time, code) in [(59, 287082), (1111111109, 81804), (1111111111, 50471), (1234567890, 5924), (2000000000, 279037), (20000000000, 353130)] {
+            assert_eq!(hotp(SECRET, time / PERIOD), code);
+        }
+    }
+
+    #[test]
+    fn used_code_is_rejected() {
+        let mut config = TotpConfig { secret: BASE32_NOPAD.encode(SECRET), last_step: 0 };
+        let step = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() / PERIOD;
+        let code = format!("{:06}", hotp(SECRET, step));
+        assert!(config.verify(&code));
+        assert_eq!(config.last_step, step);
+        assert!(!config.verify(&code));
+        // ولا رمز خطوة سابقة ما زالت داخل نافذة التسامح
+        assert!(!config.verify(&format!("{:06}", hotp(SECRET, step - 1))));
+    }
+}